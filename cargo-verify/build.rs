@@ -0,0 +1,20 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // A different id for every build of cargo-verify, so that the results
+    // that it caches are not reused by a build with different behaviour (see
+    // `cache::Cache::new`). Without 'rerun-if-changed', this script runs again
+    // whenever a file in the package changes.
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before the UNIX epoch");
+    println!("cargo:rustc-env=CARGO_VERIFY_BUILD_ID={}", time.as_nanos());
+}
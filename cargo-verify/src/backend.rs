@@ -0,0 +1,254 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// The interface between cargo-verify and the verification backends.
+//
+// To add a new backend, implement the `Backend` trait and add it to
+// `BACKENDS`.
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::*;
+
+/// A verification backend.
+pub trait Backend: Sync {
+    /// The name used for selecting the backend (`--backend=NAME`), and in
+    /// reports.
+    fn name(&self) -> &'static str;
+
+    /// Check if the backend is installed (with the tools selected by `opt`,
+    /// e.g., `--llvm-version`).
+    fn check_install(&self, opt: &Opt) -> bool;
+
+    /// The version of the backend tools (used as part of the result cache key,
+    /// so cached results are not reused after the backend is upgraded).
+    fn version(&self, _opt: &Opt) -> CVResult<String> {
+        Ok(String::new())
+    }
+
+    /// Does the backend support `--replay`?
+    fn supports_replay(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support passing arguments to the program under test?
+    fn supports_args(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support symbolic arguments (`--sym-args`)?
+    fn supports_sym_args(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support symbolic standard input (`--sym-stdin`)?
+    fn supports_sym_stdin(&self) -> bool {
+        false
+    }
+
+    /// Does the backend replay symbolic standard input (`--replay` with
+    /// `--sym-stdin`)?
+    fn supports_replay_sym_stdin(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support symbolic files (`--sym-files`)?
+    fn supports_sym_files(&self) -> bool {
+        false
+    }
+
+    /// Can the backend show that a test cannot fail (as opposed to only
+    /// finding failures, e.g., when fuzzing)? Incomplete backends report
+    /// `Status::Unknown` when they don't find a failure, their results are not
+    /// cached, and they are not part of the portfolio.
+    fn is_complete(&self) -> bool {
+        true
+    }
+
+    /// Does the backend report some failures as `Status::Error`, without
+    /// saying what they are (e.g., Seahorn only reports that an error is
+    /// reachable)? If so, `Error` matches any expected failure status.
+    fn reports_unclassified_failures(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support `--emit-regression-tests`?
+    fn supports_regression_tests(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support `--export-corpus`?
+    fn supports_export_corpus(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support `--coverage`?
+    fn supports_coverage(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support `--all-errors`?
+    fn supports_all_errors(&self) -> bool {
+        false
+    }
+
+    /// Does the backend support `--profile`?
+    fn supports_profile(&self) -> bool {
+        false
+    }
+
+    /// Does the backend verify the LLVM bitcode file generated by `build`, one
+    /// test at a time (using `verify`)? Otherwise, the backend checks the whole
+    /// crate itself (using `run`).
+    fn uses_bitcode(&self) -> bool {
+        true
+    }
+
+    /// Does the backend need the mangled name of `main` as the entry point
+    /// (instead of the C `main` function generated by rustc)?
+    fn needs_mangled_main(&self) -> bool {
+        false
+    }
+
+    /// Backend specific checks of the command line options (in addition to
+    /// the checks of the supported options done by `validate_opt`).
+    fn check_opt(&self, _opt: &Opt) -> CVResult<()> {
+        Ok(())
+    }
+
+    /// Features to activate when building the crate (e.g., "verifier-klee").
+    fn features(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Flags to add to RUSTFLAGS when building the crate.
+    fn rustflags(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Options for `rvt-patch-llvm`. If empty, the bitcode is not patched.
+    fn patch_llvm_options(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Verify the test `name`, whose entry function is `entry`, in `bcfile`.
+    fn verify(&self, opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult>;
+
+    /// Check the whole crate `package` and report the results (only used when
+    /// `uses_bitcode` is false).
+    fn run(&self, _opt: &Opt, _package: &str) -> CVResult<Status> {
+        Err(format!(
+            "The {} backend verifies bitcode files, not whole crates.",
+            self.name()
+        ))?
+    }
+}
+
+/// Make sure the command line options make sense for `backend`: reject the
+/// options that it does not support, then do its own checks (`check_opt`).
+pub fn validate_opt(backend: &dyn Backend, opt: &Opt) -> CVResult<()> {
+    if !backend.supports_args() && !opt.args.is_empty() {
+        Err(format!(
+            "The {} backend does not support passing arguments yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_sym_args() && !opt.sym_args.is_empty() {
+        Err(format!(
+            "The {} backend does not support '--sym-args' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_sym_stdin() && opt.sym_stdin.is_some() {
+        Err(format!(
+            "The {} backend does not support '--sym-stdin' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_replay_sym_stdin() && opt.replay != 0 && opt.sym_stdin.is_some() {
+        Err(format!(
+            "The {} backend does not support '--replay' with '--sym-stdin' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_sym_files() && !opt.sym_files.is_empty() {
+        Err(format!(
+            "The {} backend does not support '--sym-files' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_regression_tests() && opt.emit_regression_tests.is_some() {
+        Err(format!(
+            "The {} backend does not support '--emit-regression-tests' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_export_corpus() && opt.export_corpus.is_some() {
+        Err(format!(
+            "The {} backend does not support '--export-corpus' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_coverage() && opt.coverage.is_some() {
+        Err(format!(
+            "The {} backend does not support '--coverage' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_all_errors() && opt.all_errors {
+        Err(format!(
+            "The {} backend does not support '--all-errors' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_profile() && opt.profile {
+        Err(format!(
+            "The {} backend does not support '--profile' yet.",
+            backend.name()
+        ))?;
+    }
+    if !backend.supports_replay() && opt.replay != 0 {
+        Err(format!(
+            "The {} backend does not support '--replay' yet.",
+            backend.name()
+        ))?;
+    }
+    backend.check_opt(opt)
+}
+
+/// All the backends. When the user does not select a backend, the first one
+/// that is installed is used (Proptest is always installed).
+pub static BACKENDS: &[&dyn Backend] = &[
+    &klee::Klee,
+    &seahorn::Seahorn,
+    &proptest::Proptest,
+    &smack::Smack,
+    &crux::Crux,
+    &fuzz::Fuzz,
+];
+
+/// The pseudo-backend name that selects all the installed complete backends
+/// that use bitcode (see `--backends`).
+pub const PORTFOLIO: &str = "portfolio";
+
+/// The names of all the backends, and `PORTFOLIO`.
+pub fn names() -> Vec<&'static str> {
+    BACKENDS
+        .iter()
+        .map(|b| b.name())
+        .chain(std::iter::once(PORTFOLIO))
+        .collect()
+}
+
+/// Find the backend called `name` (ignoring case).
+pub fn find(name: &str) -> Option<&'static dyn Backend> {
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|b| b.name().eq_ignore_ascii_case(name))
+}
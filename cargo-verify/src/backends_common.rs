@@ -6,15 +6,22 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::path::Path;
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use lazy_static::lazy_static;
-use log::info;
+use log::{info, warn};
 use regex::Regex;
 use serde::Serialize;
 use tinytemplate::TinyTemplate;
 
-use crate::CVResult;
+use crate::{
+    patch_llvm, run_tools::OutputInfo, utils::Append, Backend, CVResult, Opt, Status, Verbosity,
+};
 
 /// Detect lines that match #[should_panic(expected = ...)] string.
 pub fn is_expected_panic(line: &str, expect: &Option<&str>, name: &str) -> bool {
@@ -50,6 +57,61 @@ pub fn is_expected_panic(line: &str, expect: &Option<&str>, name: &str) -> bool
     false
 }
 
+/// Find the status that the test is expected to fail with, declared by a
+/// "VERIFIER_EXPECT: status(STATUS)" line (see `verifier::expect_status`).
+pub fn expected_status(output: &str, name: &str) -> Option<Status> {
+    output.lines().find_map(|l| {
+        let status = l
+            .strip_prefix("VERIFIER_EXPECT: status(")?
+            .strip_suffix(")")?;
+        match serde_json::from_value(serde_json::Value::from(status)) {
+            Ok(status) => {
+                info!("Expecting status '{}'", status);
+                Some(status)
+            }
+            Err(_) => {
+                warn!("{}: Unknown expected status '{}'", name, status);
+                None
+            }
+        }
+    })
+}
+
+/// Compare the status of a test with the status that it is expected to fail
+/// with (if any): a test that fails as expected is verified and a test that
+/// does not fail is an error.
+///
+/// The status must match exactly, except for backends that report some
+/// failures without saying what they are (see
+/// `Backend::reports_unclassified_failures`), where `Error` counts as any
+/// expected failure other than a timeout.
+pub fn check_expected_status(
+    backend: &dyn Backend,
+    name: &str,
+    status: Status,
+    expected: Option<Status>,
+) -> Status {
+    let expected = match expected {
+        Some(expected) => expected,
+        None => return status,
+    };
+    let unclassified = backend.reports_unclassified_failures()
+        && status == Status::Error
+        && expected != Status::Timeout;
+    if status == expected || unclassified {
+        info!("     {}: Detected expected failure '{}'", name, status);
+        Status::Verified
+    } else if status == Status::Verified {
+        warn!(
+            "     {}: Expected failure '{}' did not happen",
+            name, expected
+        );
+        Status::Error
+    } else {
+        status
+    }
+}
+
 #[derive(Serialize)]
 struct FormatFlagContext<'a> {
     entry:      &'a str,
@@ -72,3 +134,152 @@ pub fn format_flag(flag: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CV
 
     Ok(res)
 }
+
+/// For backends that can't pass arguments to the program or make standard
+/// input symbolic, patch the bitcode file: generate a function that runs the
+/// initializers with the arguments (`opt.args`) and then calls `entry`, and
+/// make standard input symbolic (`opt.sym_stdin`) using the model of standard
+/// input in the runtime library. Return the new bitcode file (in
+/// `out_dir`) and entry function, or `bcfile` and `entry` if nothing needs to
+/// change.
+pub fn patch_inputs(
+    opt: &Opt,
+    bcfile: &Path,
+    entry: &str,
+    out_dir: &Path,
+) -> CVResult<(PathBuf, String)> {
+    if opt.args.is_empty() && opt.sym_stdin.is_none() {
+        return Ok((bcfile.to_path_buf(), String::from(entry)));
+    }
+
+    let mut options = Vec::new();
+    let mut new_entry = String::from(entry);
+    if !opt.args.is_empty() {
+        options.push(format!("--wrap-main={}", entry));
+        options.extend(opt.args.iter().map(|arg| format!("--arg={}", arg)));
+        new_entry = String::from("__rvt_main");
+    }
+    if let Some(size) = opt.sym_stdin {
+        options.push(format!("--sym-stdin={}", size));
+    }
+
+    let new_bcfile = out_dir.to_path_buf().append("inputs.bc");
+    let options: Vec<&str> = options.iter().map(String::as_str).collect();
+    patch_llvm(opt, &options, bcfile, &new_bcfile)?;
+    Ok((new_bcfile, new_entry))
+}
+
+/// Classify a failing test by the messages that describe the failure (e.g.,
+/// the panic message).
+pub fn failure_status(messages: &[String]) -> Status {
+    messages
+        .iter()
+        .find_map(|l| {
+            if l.contains("with overflow") {
+                Some(Status::Overflow)
+            } else if l.contains("divide by zero") || l.contains("divisor of zero") {
+                Some(Status::DivisionByZero)
+            } else if l.contains("index out of bounds") {
+                Some(Status::OutOfBounds)
+            } else if l.contains("assertion failed") {
+                Some(Status::AssertFailed)
+            } else if l.contains("unreachable") {
+                Some(Status::Reachable)
+            } else if l.contains("panicked") {
+                Some(Status::Panic)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Status::Error)
+}
+
+/// Get the version of `tool` from the output of `tool --version`.
+pub fn tool_version(opt: &Opt, tool: &str) -> CVResult<String> {
+    let (stdout, stderr, _) = Command::new(tool)
+        .arg("--version")
+        .output_info_ignore_exit(opt, Verbosity::Trivial)?;
+    Ok(stdout + &stderr)
+}
+
+/// The concrete inputs used to replay a counterexample.
+#[derive(Default)]
+pub struct ReplayInput<'a> {
+    /// Arguments passed to main after `opt.args`
+    pub args:      Vec<OsString>,
+    /// Flags added to RUSTFLAGS
+    pub rustflags: Vec<OsString>,
+    /// Environment variables (this is how the backend specific runtime gets
+    /// the concrete input values)
+    pub envs:      Vec<(&'a str, &'a OsStr)>,
+    /// A file to use as standard input
+    pub stdin:     Option<PathBuf>,
+    /// The directory to run the program in (where the contents of symbolic
+    /// files are written)
+    pub dir:       Option<PathBuf>,
+}
+
+/// Replay the test `name` (or main) by building and running it natively, with
+/// `cargo test` (or `cargo run`), using the concrete values in `input`. The
+/// output of the test is printed and returned.
+pub fn replay(opt: &Opt, name: &str, input: &ReplayInput) -> CVResult<String> {
+    let mut cmd = Command::new("cargo");
+
+    // The manifest path must not be relative to the directory that the
+    // program runs in.
+    let cargo_toml = if input.dir.is_some() {
+        fs::canonicalize(&opt.cargo_toml)?
+    } else {
+        opt.cargo_toml.clone()
+    };
+
+    if opt.tests || !opt.test.is_empty() {
+        cmd.arg("test").arg("--manifest-path").arg(&cargo_toml);
+
+        if !opt.features.is_empty() {
+            cmd.arg("--features").arg(opt.features.join(","));
+        }
+
+        cmd.arg(name).args(["--", "--nocapture"]);
+    } else {
+        cmd.arg("run").arg("--manifest-path").arg(&cargo_toml);
+
+        if !opt.features.is_empty() {
+            cmd.arg("--features").arg(opt.features.join(","));
+        }
+
+        if !opt.args.is_empty() || !input.args.is_empty() {
+            cmd.arg("--").args(opt.args.iter()).args(&input.args);
+        }
+    }
+
+    let mut env_rustflags = match std::env::var_os("RUSTFLAGS") {
+        Some(env_rustflags) => env_rustflags.append(" --cfg=verify"),
+        None => OsString::from("--cfg=verify"),
+    };
+    for flag in &input.rustflags {
+        env_rustflags = env_rustflags.append(" ").append(flag);
+    }
+    cmd.env("RUSTFLAGS", env_rustflags)
+        .envs(input.envs.iter().copied());
+
+    if let Some(stdin) = &input.stdin {
+        cmd.stdin(fs::File::open(stdin)?);
+    }
+    if let Some(dir) = &input.dir {
+        cmd.current_dir(dir);
+    }
+
+    // Note that we do not treat this as an error, because
+    // the interesting case for replay is when the backend had found an error.
+    let (stdout, stderr, _success) = cmd.output_info_ignore_exit(opt, Verbosity::Major)?;
+
+    let mut output = String::new();
+    for line in stdout.lines().chain(stderr.lines()) {
+        output!(&opt, "{}", line);
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
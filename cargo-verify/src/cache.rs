@@ -0,0 +1,120 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// A cache of verification results, so that tests that did not change since
+// the previous run are not verified again.
+//
+// The results are stored in "{target_dir}/verify-cache", one JSON file per
+// test. The file name is a hash of everything that can affect the result: the
+// build of cargo-verify, the (patched) bitcode file, the entry function, the
+// backend and its version and configuration, and the command line options that
+// are passed to the backend.
+////////////////////////////////////////////////////////////////////////////////
+
+use sha2::{Digest, Sha256};
+
+use crate::*;
+
+/// The cached results of tests in a single bitcode file.
+pub struct Cache {
+    dir: PathBuf,
+    /// Hash of the bitcode file, backend and options
+    key: String,
+}
+
+impl Cache {
+    /// Open the cache for the bitcode file `bcfile` that was built for
+    /// `backend`.
+    pub fn new(opt: &Opt, backend: &dyn Backend, bcfile: &Path) -> CVResult<Cache> {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("cargo-verify {}\n", env!("CARGO_VERIFY_BUILD_ID")));
+        hasher.update(format!("{}\n{}\n", backend.name(), backend.version(opt)?));
+        if let Some(dir) = &opt.seahorn_verify_c_common_dir {
+            // Seahorn's configuration is read from this directory.
+            hasher.update(format!("{}\n", dir));
+            let config = Path::new(dir).join("seahorn").join("sea_base.yaml");
+            hasher.update(fs::read(config).unwrap_or_default());
+        }
+        hasher.update(format!(
+            "{:?} {} {:?}\n",
+            opt.backend_flags, opt.replace_backend_flags, opt.args
+        ));
+        hasher.update(format!(
+            "{:?} {:?} {:?}\n",
+            opt.sym_args, opt.sym_stdin, opt.sym_files
+        ));
+        hasher.update(format!("{}\n", opt.all_errors));
+        hasher.update(fs::read(bcfile)?);
+
+        Ok(Cache {
+            dir: cache_dir(opt)?,
+            key: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    /// The file that holds the result of the test whose entry function is
+    /// `entry`.
+    fn file(&self, entry: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(entry);
+        self.dir
+            .clone()
+            .append(format!("{:x}.json", hasher.finalize()))
+    }
+
+    /// Get the cached result of the test whose entry function is `entry`, if
+    /// there is one.
+    pub fn lookup(&self, backend: &'static dyn Backend, entry: &str) -> Option<TestResult> {
+        let json = fs::read_to_string(self.file(entry)).ok()?;
+        let mut result: TestResult = serde_json::from_str(&json).ok()?;
+        result.backend = backend.name();
+        Some(result)
+    }
+
+    /// Cache `result`. Only conclusive results are cached; the backend might
+    /// succeed next time when it timed out or failed to run.
+    pub fn store(&self, result: &TestResult) -> CVResult<()> {
+        if matches!(
+            result.status,
+            Status::Unknown | Status::Timeout | Status::OutOfMemory
+        ) {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first, so a concurrent (or interrupted)
+        // run never reads a partial result.
+        let file = self.file(&result.entry);
+        let tmp = file.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(result)?)?;
+        fs::rename(&tmp, &file)?;
+        Ok(())
+    }
+}
+
+/// The directory where the results are cached.
+fn cache_dir(opt: &Opt) -> CVResult<PathBuf> {
+    Ok(get_meta_target_directory(opt)?.append("verify-cache"))
+}
+
+/// Remove all the cached results.
+pub fn clear(opt: &Opt) -> CVResult<()> {
+    let dir = cache_dir(opt)?;
+    info_at!(
+        &opt,
+        Verbosity::Informative,
+        "Removing cached results from {}",
+        dir.to_string_lossy()
+    );
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Structured counterexamples, saved when a test fails so that they can be
+// displayed later (with '--show-counterexample') without running the backend
+// again.
+//
+// The counterexamples of a test are stored in
+// "{target_dir}/verify-counterexamples/{test}.json".
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::*;
+
+/// A named input value (as printed by `proptest!` and `verifier::nondet_named`
+/// when replaying).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Value {
+    /// The name of the value (and its type, if known)
+    pub name:  String,
+    /// The `Debug` string of the value
+    pub value: String,
+}
+
+impl Value {
+    /// Decode a raw input object, whose type is not known: objects with the
+    /// size of an integer are shown as (little-endian, unsigned) integers, and
+    /// the others as bytes.
+    pub fn from_object(name: &str, bytes: &[u8]) -> Value {
+        let value = match bytes.len() {
+            1 | 2 | 4 | 8 | 16 => bytes
+                .iter()
+                .rev()
+                .fold(0u128, |n, b| n << 8 | u128::from(*b))
+                .to_string(),
+            _ => format!("{:?}", bytes),
+        };
+        Value {
+            name: String::from(name),
+            value,
+        }
+    }
+}
+
+/// A raw input object (e.g., a KLEE ktest object).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Object {
+    pub name:  String,
+    pub bytes: Vec<u8>,
+}
+
+/// A concrete input that makes a test fail.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Counterexample {
+    /// Name of the test (without the crate name)
+    pub name:    String,
+    pub backend: String,
+    pub status:  Status,
+    /// The file that the backend generated (e.g., a ktest file)
+    pub file:    PathBuf,
+    /// The values of the inputs (with their types only if the counterexample
+    /// was replayed)
+    pub values:  Vec<Value>,
+    pub objects: Vec<Object>,
+    /// Source location of the panic ("file:line:column") or, if the test
+    /// failed for another reason, of the failure ("file:line")
+    pub panic:   Option<String>,
+}
+
+impl Counterexample {
+    pub fn new(result: &TestResult, file: &Path) -> Counterexample {
+        Counterexample {
+            name:    result.name.clone(),
+            backend: String::from(result.backend),
+            status:  result.status,
+            file:    file.to_path_buf(),
+            values:  Vec::new(),
+            objects: Vec::new(),
+            panic:   panic_location(result.messages.iter().map(String::as_str)),
+        }
+    }
+
+    /// Add the values and panic location from the output of replaying the
+    /// counterexample (replacing the values decoded from the objects).
+    pub fn add_replay_output(&mut self, output: &str) {
+        lazy_static! {
+            static ref VALUE: Regex = Regex::new(r"^\s*Value (.+?) = (.*)$").unwrap();
+        }
+
+        self.values = output
+            .lines()
+            .filter_map(|l| {
+                let caps = VALUE.captures(l)?;
+                Some(Value {
+                    name:  caps[1].to_string(),
+                    value: caps[2].to_string(),
+                })
+            })
+            .collect();
+        if let Some(panic) = panic_location(output.lines()) {
+            self.panic = Some(panic);
+        }
+    }
+}
+
+/// Find the location of the first panic in some output.
+fn panic_location<'a>(mut lines: impl Iterator<Item = &'a str>) -> Option<String> {
+    lazy_static! {
+        static ref PANICKED: Regex = Regex::new(r" panicked at '[^']*',\s+(\S+)").unwrap();
+    }
+    lines.find_map(|l| Some(PANICKED.captures(l)?[1].to_string()))
+}
+
+/// The directory where the counterexamples are saved.
+fn counterexample_dir(opt: &Opt) -> CVResult<PathBuf> {
+    Ok(get_meta_target_directory(opt)?.append("verify-counterexamples"))
+}
+
+/// Save the counterexamples of the test of `result`, replacing any
+/// counterexamples from previous runs. The inputs of the expected failures of
+/// a verified test (e.g., `should_panic`) are not counterexamples, so nothing is
+/// saved for it.
+pub fn save(opt: &Opt, result: &TestResult, counterexamples: &[Counterexample]) -> CVResult<()> {
+    let file = counterexample_dir(opt)?.append(format!("{}.json", result.name));
+    if counterexamples.is_empty() || result.status == Status::Verified {
+        if file.exists() {
+            fs::remove_file(&file)?;
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(&file, serde_json::to_string_pretty(counterexamples)?)?;
+    Ok(())
+}
+
+/// Print the counterexamples of the test `name` that were saved by a previous
+/// run.
+pub fn show(opt: &Opt, name: &str) -> CVResult<()> {
+    let file = counterexample_dir(opt)?.append(format!("{}.json", name));
+    let json = fs::read_to_string(&file)
+        .map_err(|_| format!("There is no counterexample for '{}'.", name))?;
+    let counterexamples: Vec<Counterexample> = serde_json::from_str(&json)?;
+
+    if opt.message_format == MessageFormat::Json {
+        println!("{}", serde_json::to_string(&counterexamples)?);
+        return Ok(());
+    }
+
+    for c in counterexamples {
+        println!("{} ({}): {}", c.name, c.backend, c.status);
+        println!("  Test input {}", c.file.to_string_lossy());
+        if let Some(panic) = &c.panic {
+            println!("  Panic at {}", panic);
+        }
+        for v in &c.values {
+            println!("  Value {} = {}", v.name, v.value);
+        }
+        for o in &c.objects {
+            println!("  Object {} = {:?}", o.name, o.bytes);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,347 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Coverage reports ('--coverage DIR') built from the statistics that KLEE
+// writes to "run.istats".
+//
+// "run.istats" is in callgrind format: "fl=" and "fn=" lines select the
+// current source file and function, and each cost line gives an instruction
+// number, a source line and one value per event listed in the "events:"
+// header. We use the "Icov" (instruction covered) event to compute which
+// instructions, and so which source lines, were executed.
+//
+// For each test, an lcov tracefile "{DIR}/{test}.info" is written and, after
+// all tests have been run, the coverage of all tests is merged into
+// "{DIR}/coverage.info". If 'genhtml' is installed, it is used to render the
+// merged tracefile as HTML in "{DIR}/html".
+//
+// KLEE does not record which way each branch went, so there is no branch
+// coverage as such (the tracefiles have no "BRDA" records, see
+// docs/tools.md). Instead, lines where some (but not all) instructions were
+// executed are reported as partially covered: these are usually branches that
+// were not explored.
+//
+// The coverage of different tests is merged instruction by instruction. This
+// relies on KLEE numbering the instructions in the same way for every test,
+// which it does because all the tests are in the same bitcode file.
+////////////////////////////////////////////////////////////////////////////////
+
+use std::{collections::BTreeMap, process::Command};
+
+use log::warn;
+
+use crate::*;
+
+/// The coverage of one source line
+#[derive(Clone, Default)]
+struct Line {
+    /// Whether each instruction of the line (by its number in KLEE's
+    /// "assembly.ll") was executed
+    instructions: BTreeMap<u64, bool>,
+}
+
+impl Line {
+    /// Was any instruction executed?
+    fn is_hit(&self) -> bool {
+        self.instructions.values().any(|covered| *covered)
+    }
+
+    /// Were some, but not all, of the instructions executed?
+    fn is_partial(&self) -> bool {
+        self.is_hit() && self.instructions.values().any(|covered| !covered)
+    }
+}
+
+/// Line coverage of all the source files of a test (or of several tests)
+#[derive(Default)]
+struct Coverage {
+    files: BTreeMap<String, BTreeMap<u64, Line>>,
+}
+
+impl Coverage {
+    /// Read the coverage from a KLEE "run.istats" file. Relative source file
+    /// names are relative to `crate_dir` (where rustc ran), and source files
+    /// that don't exist (e.g., the Rust standard library) are ignored.
+    fn read_istats(file: &Path, crate_dir: &Path) -> CVResult<Coverage> {
+        let istats = fs::read_to_string(file)?;
+
+        let mut coverage = Coverage::default();
+        let mut icov = None;
+        let mut current: Option<PathBuf> = None;
+        // The cost line after "calls=" gives the inclusive cost of a call.
+        let mut skip_next = false;
+
+        for l in istats.lines() {
+            if let Some(events) = l.strip_prefix("events:") {
+                let events: Vec<&str> = events.split_whitespace().collect();
+                icov = events.iter().position(|e| *e == "Icov");
+            } else if let Some(fl) = l.strip_prefix("fl=") {
+                let source = crate_dir.join(fl);
+                current = if source.is_file() { Some(source) } else { None };
+            } else if l.starts_with("calls=") {
+                skip_next = true;
+            } else if l.starts_with(|c: char| c.is_ascii_digit()) {
+                if std::mem::replace(&mut skip_next, false) {
+                    continue;
+                }
+                let (file, icov) = match (&current, icov) {
+                    (Some(file), Some(icov)) => (file, icov),
+                    _ => continue,
+                };
+                // "{instruction} {line} {event values...}"
+                let values: Vec<u64> = l
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+                let (instruction, line) = match values.as_slice() {
+                    [instruction, line, ..] if *line != 0 => (*instruction, *line),
+                    _ => continue,
+                };
+                let covered = matches!(values.get(icov + 2), Some(v) if *v > 0);
+                *coverage
+                    .files
+                    .entry(file.to_string_lossy().into_owned())
+                    .or_default()
+                    .entry(line)
+                    .or_default()
+                    .instructions
+                    .entry(instruction)
+                    .or_default() |= covered;
+            }
+        }
+        if icov.is_none() {
+            Err(format!(
+                "No coverage information in {}",
+                file.to_string_lossy()
+            ))?;
+        }
+        Ok(coverage)
+    }
+
+    /// Add the coverage of another test.
+    /// An instruction is covered if it was covered by either test.
+    fn merge(&mut self, other: &Coverage) {
+        for (file, lines) in &other.files {
+            let mine = self.files.entry(file.clone()).or_default();
+            for (n, line) in lines {
+                let entry = mine.entry(*n).or_default();
+                for (instruction, covered) in &line.instructions {
+                    *entry.instructions.entry(*instruction).or_default() |= covered;
+                }
+            }
+        }
+    }
+
+    /// Return the number of lines, of lines executed and of lines partially
+    /// executed.
+    fn summary(&self) -> (usize, usize, usize) {
+        let lines = self.files.values().flat_map(BTreeMap::values);
+        let total = lines.clone().count();
+        let hit = lines.clone().filter(|l| l.is_hit()).count();
+        let partial = lines.filter(|l| l.is_partial()).count();
+        (total, hit, partial)
+    }
+
+    /// Write the coverage as an lcov tracefile.
+    fn write_lcov(&self, test: &str, file: &Path) -> CVResult<()> {
+        let mut info = String::new();
+        for (source, lines) in &self.files {
+            info.push_str(&format!("TN:{}\nSF:{}\n", test.replace("::", "_"), source));
+            for (n, line) in lines {
+                // KLEE does not count how often a line is executed.
+                info.push_str(&format!("DA:{},{}\n", n, line.is_hit() as u8));
+            }
+            info.push_str(&format!(
+                "LF:{}\nLH:{}\nend_of_record\n",
+                lines.len(),
+                lines.values().filter(|l| l.is_hit()).count()
+            ));
+        }
+        fs::write(file, info)?;
+        Ok(())
+    }
+}
+
+/// The directory of the crate, where rustc ran (so the source file names in
+/// the debug information are relative to it).
+fn crate_dir(opt: &Opt) -> PathBuf {
+    match opt.cargo_toml.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Write the coverage report of the test `name` from the KLEE output in
+/// `out_dir`.
+pub fn report_test(opt: &Opt, name: &str, out_dir: &Path) -> CVResult<()> {
+    let dir = match &opt.coverage {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    let istats = out_dir.to_path_buf().append("run.istats");
+    if !istats.is_file() {
+        warn!("No coverage information for {}", name);
+        return Ok(());
+    }
+    let coverage = Coverage::read_istats(&istats, &crate_dir(opt))?;
+
+    fs::create_dir_all(dir)?;
+    let file = dir.clone().append(format!("{}.info", name));
+    let (total, hit, partial) = coverage.summary();
+    info_at!(
+        &opt,
+        Verbosity::Informative,
+        "  Coverage of {}: {}/{} lines ({} partially covered) in {}",
+        name,
+        hit,
+        total,
+        partial,
+        file.to_string_lossy()
+    );
+    coverage.write_lcov(name, &file)
+}
+
+/// Merge the coverage of all the tests in `results` and write the
+/// aggregated report (and an HTML version if 'genhtml' is available).
+pub fn report_all(opt: &Opt, results: &[TestResult]) -> CVResult<()> {
+    let dir = match &opt.coverage {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    let mut coverage = Coverage::default();
+    for result in results {
+        let istats = match &result.output_dir {
+            Some(out_dir) => out_dir.clone().append("run.istats"),
+            None => continue,
+        };
+        if istats.is_file() {
+            coverage.merge(&Coverage::read_istats(&istats, &crate_dir(opt))?);
+        }
+    }
+
+    fs::create_dir_all(dir)?;
+    let file = dir.clone().append("coverage.info");
+    coverage.write_lcov("all", &file)?;
+
+    let (total, hit, partial) = coverage.summary();
+    if opt.message_format == MessageFormat::Human {
+        println!(
+            "Coverage: {}/{} lines ({} partially covered) in {}",
+            hit,
+            total,
+            partial,
+            file.to_string_lossy()
+        );
+    }
+
+    let html = dir.clone().append("html");
+    match Command::new("genhtml")
+        .arg("--quiet")
+        .arg("--output-directory")
+        .arg(&html)
+        .arg(&file)
+        .output()
+    {
+        Ok(output) if output.status.success() => info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  Wrote HTML coverage report to {}",
+            html.to_string_lossy()
+        ),
+        Ok(output) => warn!(
+            "genhtml failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  genhtml is not installed: no HTML coverage report"
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read each of `istats` as the coverage of a test in a crate with a
+    /// single source file ("src/main.rs").
+    fn read(istats: &[&str], test: &str) -> Vec<Coverage> {
+        let crate_dir = std::env::temp_dir().append(format!(
+            "cargo-verify-coverage-{}-{}",
+            std::process::id(),
+            test
+        ));
+        fs::create_dir_all(crate_dir.clone().append("src")).unwrap();
+        fs::write(crate_dir.clone().append("src/main.rs"), "").unwrap();
+        let file = crate_dir.clone().append("run.istats");
+        let coverage = istats
+            .iter()
+            .map(|istats| {
+                fs::write(&file, istats).unwrap();
+                Coverage::read_istats(&file, &crate_dir).unwrap()
+            })
+            .collect();
+        fs::remove_dir_all(&crate_dir).unwrap();
+        coverage
+    }
+
+    const ISTATS: &str = "\
+version: 1
+creator: klee
+positions: instr line
+events: Icov Forks Ireal
+ob=main.bc
+fl=src/main.rs
+fn=main
+1 3 1 0 1
+2 3 0 0 0
+3 4 1 0 1
+calls=1 10 5
+4 5 100 0 100
+5 6 1 0 1
+fl=/rustc/library/core/src/lib.rs
+fn=core
+6 10 1 0 1
+";
+
+    #[test]
+    fn read_istats() {
+        let coverage = &read(&[ISTATS], "read")[0];
+        // Line 3 is partially covered, the inclusive cost of the call on line
+        // 5 is not a line of its own, and the standard library is ignored.
+        assert_eq!(coverage.summary(), (3, 3, 1));
+        assert_eq!(coverage.files.len(), 1);
+    }
+
+    #[test]
+    fn merge() {
+        // Another test covers the rest of line 3.
+        let other = ISTATS.replace("2 3 0 0 0", "2 3 1 0 1");
+        let mut coverage = read(&[ISTATS, &other], "merge");
+        let other = coverage.pop().unwrap();
+        coverage[0].merge(&other);
+        assert_eq!(coverage[0].summary(), (3, 3, 0));
+    }
+
+    #[test]
+    fn no_icov() {
+        let crate_dir = std::env::temp_dir();
+        let file = crate_dir.clone().append(format!(
+            "cargo-verify-coverage-{}.istats",
+            std::process::id()
+        ));
+        fs::write(&file, "events: Forks\n").unwrap();
+        let result = Coverage::read_istats(&file, &crate_dir);
+        fs::remove_file(&file).unwrap();
+        assert!(result.is_err());
+    }
+}
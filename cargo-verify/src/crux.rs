@@ -0,0 +1,279 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::process::Command;
+
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+
+use crate::*;
+
+pub struct Crux;
+
+impl Backend for Crux {
+    fn name(&self) -> &'static str {
+        "crux"
+    }
+
+    /// Check if crux-mir's cargo subcommand is available.
+    fn check_install(&self, _opt: &Opt) -> bool {
+        let output = Command::new("which").arg("cargo-crux-test").output().ok();
+
+        match output {
+            Some(output) => output.status.success(),
+            None => false,
+        }
+    }
+
+    fn uses_bitcode(&self) -> bool {
+        false
+    }
+
+    fn check_opt(&self, opt: &Opt) -> CVResult<()> {
+        if opt.output.is_some() {
+            Err("backend crux does not support --output")?;
+        }
+        if !opt.tests && opt.test.is_empty() {
+            Err("The crux backend can only verify tests (use '--tests' or '--test').")?;
+        }
+        Ok(())
+    }
+
+    fn features(&self) -> &'static [&'static str] {
+        &["verifier-crux"]
+    }
+
+    fn verify(
+        &self,
+        _opt: &Opt,
+        _name: &str,
+        _entry: &str,
+        _bcfile: &Path,
+    ) -> CVResult<TestResult> {
+        // `uses_bitcode` is false, so `run` is used instead.
+        Err("The crux backend verifies whole crates, not bitcode files.")?
+    }
+
+    fn run(&self, opt: &Opt, package: &str) -> CVResult<Status> {
+        let beginning = Instant::now();
+
+        let results = run(opt)?;
+        if results.is_empty() {
+            Err("  No tests found")?
+        }
+
+        if opt.message_format == MessageFormat::Human {
+            println!("Running {} test(s)", results.len());
+        }
+        for result in &results {
+            crate::report_test(opt, result);
+        }
+
+        crate::report_results(opt, package, &results, beginning.elapsed().as_secs_f32())
+    }
+}
+
+/// Run `cargo crux-test` and analyse its output.
+/// Return the results of the tests selected by `--test`.
+fn run(opt: &Opt) -> CVResult<Vec<TestResult>> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("crux-test")
+        .arg("--manifest-path")
+        .arg(&opt.cargo_toml)
+        .args(vec!["-v"; opt.verbose]);
+
+    if !opt.features.is_empty() {
+        cmd.arg("--features").arg(opt.features.join(","));
+    }
+
+    // Like 'cargo test', 'cargo crux-test' only takes one test name filter:
+    // with several '--test' options, all the tests are run and filtered below.
+    if let [filter] = opt.test.as_slice() {
+        cmd.arg(filter);
+    }
+
+    if !opt.backend_flags.is_empty() {
+        cmd.arg("--").args(&opt.backend_flags);
+    }
+
+    // Crates that use cfg(verify) to select verification dependencies need it
+    // set, as with the other backends.
+    let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+    rustflags.push_str(" --cfg=verify");
+    cmd.env("RUSTFLAGS", rustflags.trim());
+
+    let (stdout, stderr, _) = cmd.limited_output_info(opt, Verbosity::Major)?;
+
+    let mut results = parse_output(&stdout);
+    if results.is_empty() {
+        // Most likely a compilation error
+        for l in stderr.lines() {
+            warn!("{}", l);
+        }
+        Err("crux-mir did not report any test results")?
+    }
+
+    if !opt.test.is_empty() {
+        results.retain(|r| opt.test.iter().any(|f| r.name.contains(f)));
+    }
+
+    // crux-mir does not generate counterexamples: remove the ones saved by
+    // other backends, which are about a different run.
+    for result in &results {
+        counterexample::save(opt, result, &[])?;
+    }
+
+    Ok(results)
+}
+
+/// Parse the output of `cargo crux-test`, which looks like this
+///
+/// ```text
+/// test mycrate/3a1fbbbh::t1[0]: ok
+/// test mycrate/3a1fbbbh::t2[0]: FAILED
+///
+/// failures:
+///
+/// ---- mycrate/3a1fbbbh::t2[0] counterexamples ----
+/// Failure for MIR assertion at src/lib.rs:13:13:
+///         attempt to add with overflow
+/// ```
+fn parse_output(stdout: &str) -> Vec<TestResult> {
+    lazy_static! {
+        static ref TEST: Regex = Regex::new(r"^test (\S+): (ok|FAILED|returned .*)$").unwrap();
+        static ref COUNTEREXAMPLES: Regex =
+            Regex::new(r"^---- (\S+) counterexamples ----$").unwrap();
+    }
+
+    let mut results: Vec<TestResult> = Vec::new();
+    // The expected status of each test (see `verifier::expect_status`)
+    let mut expected: Vec<Option<Status>> = Vec::new();
+    // The test whose counterexamples are being read
+    let mut current: Option<usize> = None;
+    // The expected status printed by the test that is running
+    let mut expecting: Option<Status> = None;
+
+    for l in stdout.lines() {
+        if let Some(caps) = TEST.captures(l) {
+            let entry = caps.get(1).unwrap().as_str();
+            let status = match caps.get(2).unwrap().as_str() {
+                "FAILED" => Status::Error,
+                _ => Status::Verified,
+            };
+            results.push(TestResult::new(&Crux, &test_name(entry), entry, status));
+            expected.push(expecting.take());
+            current = None;
+        } else if let Some(i) = l.find("VERIFIER_EXPECT: ") {
+            // The test's name is not known until its result is printed.
+            expecting = backends_common::expected_status(&l[i..], "crux-mir");
+        } else if let Some(caps) = COUNTEREXAMPLES.captures(l) {
+            let entry = caps.get(1).unwrap().as_str();
+            current = results.iter().position(|r| r.entry == entry);
+        } else if l.starts_with("test result:") || l == "failures:" {
+            current = None;
+        } else if let Some(i) = current {
+            let l = l.trim();
+            if !l.is_empty() {
+                results[i].messages.push(String::from(l));
+            }
+        }
+    }
+
+    for (result, expected) in results.iter_mut().zip(expected) {
+        if result.status != Status::Verified {
+            result.status = backends_common::failure_status(&result.messages);
+        }
+        result.status =
+            backends_common::check_expected_status(&Crux, &result.name, result.status, expected);
+        if result.status != Status::Verified {
+            info!("{}: {}", result.name, result.status);
+        }
+    }
+
+    results
+}
+
+/// Turn a crux-mir test path such as "mycrate/3a1fbbbh::tests::t1[0]" into
+/// the name used by the other backends ("tests::t1").
+fn test_name(entry: &str) -> String {
+    let name = match entry.find("::") {
+        Some(i) => &entry[i + 2..],
+        None => entry,
+    };
+    let name = match name.rfind('[') {
+        Some(i) if name.ends_with(']') => &name[..i],
+        _ => name,
+    };
+    String::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let stdout = "\
+running 4 tests
+test mycrate/3a1fbbbh::t1[0]: ok
+test mycrate/3a1fbbbh::tests::t2[0]: FAILED
+VERIFIER_EXPECT: status(OVERFLOW)
+test mycrate/3a1fbbbh::t3[0]: FAILED
+test mycrate/3a1fbbbh::t4[0]: returned 42
+
+failures:
+
+---- mycrate/3a1fbbbh::tests::t2[0] counterexamples ----
+Failure for MIR assertion at src/lib.rs:13:13:
+        attempt to add with overflow
+
+---- mycrate/3a1fbbbh::t3[0] counterexamples ----
+Failure for MIR assertion at src/lib.rs:21:13:
+        attempt to multiply with overflow
+
+test result: FAILED. 2 passed; 2 failed
+";
+        let results = parse_output(stdout);
+        let results: Vec<(&str, Status)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("t1", Status::Verified),
+                ("tests::t2", Status::Overflow),
+                // The expected failure is printed before the test's result.
+                ("t3", Status::Verified),
+                ("t4", Status::Verified),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let stdout = "\
+test mycrate/3a1fbbbh::t2[0]: FAILED
+
+failures:
+
+---- mycrate/3a1fbbbh::t2[0] counterexamples ----
+Failure for MIR assertion at src/lib.rs:13:13:
+        attempt to add with overflow
+";
+        let results = parse_output(stdout);
+        assert_eq!(
+            results[0].messages,
+            vec![
+                "Failure for MIR assertion at src/lib.rs:13:13:",
+                "attempt to add with overflow"
+            ]
+        );
+    }
+}
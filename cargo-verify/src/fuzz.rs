@@ -0,0 +1,302 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// The fuzzing backend: each test is linked with libFuzzer as a fuzz target,
+// and the non-deterministic values are taken from the fuzzer's input (see
+// the "verifier-fuzz" feature of verification-annotations).
+////////////////////////////////////////////////////////////////////////////////
+
+use std::{collections::HashMap, fs, path::Path, process::Command};
+
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+
+use crate::{
+    counterexample::{self, Counterexample},
+    utils::Append,
+    *,
+};
+
+/// How long to fuzz each test for (in seconds) if there is no `--fuzz-time`.
+const DEFAULT_FUZZ_TIME: u64 = 60;
+
+pub struct Fuzz;
+
+impl Backend for Fuzz {
+    fn name(&self) -> &'static str {
+        "fuzz"
+    }
+
+    /// libFuzzer is part of clang, which is used to link the fuzz target.
+    fn check_install(&self, opt: &Opt) -> bool {
+        let output = Command::new("which")
+            .arg(format!("clang-{}", opt.llvm_version))
+            .output()
+            .ok();
+
+        match output {
+            Some(output) => output.status.success(),
+            None => false,
+        }
+    }
+
+    fn supports_replay(&self) -> bool {
+        true
+    }
+
+    fn needs_mangled_main(&self) -> bool {
+        true
+    }
+
+    fn is_complete(&self) -> bool {
+        false
+    }
+
+    fn features(&self) -> &'static [&'static str] {
+        &["verifier-fuzz"]
+    }
+
+    fn rustflags(&self) -> &'static [&'static str] {
+        // Rejected inputs unwind to the fuzz target (see the "verifier-fuzz"
+        // feature of verification-annotations) so that the memory that the
+        // test allocated is freed.
+        &["-Cpanic=unwind"]
+    }
+
+    fn patch_llvm_options(&self) -> &'static [&'static str] {
+        &["--fuzz"]
+    }
+
+    fn verify(&self, opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
+        verify(opt, name, entry, bcfile)
+    }
+}
+
+/// Build a fuzz target for the test, run it and replay
+fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
+    let out_dir = opt.cargo_toml.with_file_name("fuzzout").append(name);
+
+    // Ignoring result. We don't care if it fails because the path doesn't
+    // exist.
+    fs::remove_dir_all(&out_dir).unwrap_or_default();
+    if out_dir.exists() {
+        Err(format!(
+            "Directory or file '{}' already exists, and can't be removed",
+            out_dir.to_string_lossy()
+        ))?
+    }
+    let corpus_dir = out_dir.clone().append("corpus");
+    fs::create_dir_all(&corpus_dir)?;
+
+    info!("     Fuzzing {}", name);
+    info!("      file: {}", bcfile.to_string_lossy());
+    info!("      entry: {}", entry);
+    info!("      results: {}", out_dir.to_string_lossy());
+
+    let fuzz_target = build_fuzz_target(opt, entry, bcfile, &out_dir)?;
+    let (status, stats, messages, crashes) = run(opt, name, &fuzz_target, &out_dir, &corpus_dir)?;
+
+    let mut result = TestResult::new(&Fuzz, name, entry, status);
+    if status == Status::Unknown {
+        if let Some(runs) = stats.get("runs") {
+            result.message = Some(format!("no failing input found in {} runs", runs));
+        }
+    }
+    result.stats = stats;
+    result.output_dir = Some(out_dir);
+    result.messages = messages;
+
+    let mut counterexamples = crashes
+        .iter()
+        .map(|crash| {
+            let mut counterexample = Counterexample::new(&result, crash);
+            counterexample.objects = vec![counterexample::Object {
+                name:  String::from("input"),
+                bytes: fs::read(crash)?,
+            }];
+            Ok(counterexample)
+        })
+        .collect::<CVResult<Vec<_>>>()?;
+
+    if opt.replay > 0 && status != Status::Verified {
+        for c in counterexamples.iter_mut() {
+            output!(&opt, "    Test input {}", c.file.to_str().unwrap_or("???"));
+            match replay_fuzz(opt, name, &c.file) {
+                Ok(output) => c.add_replay_output(&output),
+                Err(err) => warn!("Failed to replay: {}", err),
+            }
+        }
+    }
+    counterexample::save(opt, &result, &counterexamples)?;
+
+    result.counterexamples = crashes;
+    Ok(result)
+}
+
+/// Link `bcfile` with libFuzzer and a function that runs the test `entry` for
+/// each input. Return the executable.
+fn build_fuzz_target(opt: &Opt, entry: &str, bcfile: &Path, out_dir: &Path) -> CVResult<PathBuf> {
+    let harness = out_dir.to_path_buf().append("fuzz-target.c");
+    fs::write(
+        &harness,
+        format!(
+            "// Generated by cargo-verify: run '{entry}' with each input from libFuzzer.\n\
+             #include <stddef.h>\n\
+             #include <stdint.h>\n\
+             extern void __rvt_fuzz_run(const uint8_t *data, size_t size, void (*test)(void));\n\
+             extern void {entry}(void);\n\
+             int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size) {{\n\
+             \x20 __rvt_fuzz_run(data, size, {entry});\n\
+             \x20 return 0;\n\
+             }}\n",
+            entry = entry
+        ),
+    )?;
+
+    let fuzz_target = out_dir.to_path_buf().append("fuzz-target");
+    Command::new(format!("clang-{}", opt.llvm_version))
+        .args(["-g", "-fsanitize=fuzzer"])
+        .arg(&harness)
+        .arg(bcfile)
+        .arg("-o")
+        .arg(&fuzz_target)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .output_info(opt, Verbosity::Major)?;
+    Ok(fuzz_target)
+}
+
+/// The status, statistics, the lines of output that describe errors and the
+/// inputs that crashed the test.
+type Outcome = (Status, HashMap<String, isize>, Vec<String>, Vec<PathBuf>);
+
+/// Run the fuzz target and analyse its output.
+fn run(
+    opt: &Opt,
+    name: &str,
+    fuzz_target: &Path,
+    out_dir: &Path,
+    corpus_dir: &Path,
+) -> CVResult<Outcome> {
+    let mut cmd = Command::new(fuzz_target);
+
+    let user_flags: Vec<_> = opt
+        .backend_flags
+        .iter()
+        .map(|flag| backends_common::format_flag(flag, name, fuzz_target, out_dir))
+        .collect::<Result<_, _>>()?;
+
+    if !opt.replace_backend_flags {
+        // The time budget is passed to libFuzzer (instead of killing it) so
+        // that it can report what it found.
+        cmd.arg(format!(
+            "-max_total_time={}",
+            opt.fuzz_time.unwrap_or(DEFAULT_FUZZ_TIME)
+        ))
+        .arg(format!(
+            "-artifact_prefix={}/",
+            out_dir.to_str().ok_or("not UTF-8")?
+        ));
+        if let Some(mb) = opt.memory_limit {
+            cmd.arg(format!("-rss_limit_mb={}", mb));
+        }
+        cmd.args(user_flags).arg(corpus_dir);
+    } else {
+        cmd.args(user_flags);
+    }
+
+    // The test's output can be arbitrary bytes.
+    // Like the other backends, the fuzzer is killed if it exceeds '--timeout'.
+    let (stdout, stderr, _) =
+        cmd.output_info_helper(opt, Verbosity::Major, utils::from_latin1, true, true)?;
+
+    // Scan for expectation message
+    let mut expect = None;
+    for l in stderr.lines() {
+        if l == "VERIFIER_EXPECT: should_panic" {
+            expect = Some("");
+        } else if let Some(e) = l
+            .strip_prefix("VERIFIER_EXPECT: should_panic(expected = \"")
+            .and_then(|l| l.strip_suffix("\")"))
+        {
+            info!("Expecting '{}'", e);
+            expect = Some(e);
+        }
+    }
+    let expected = backends_common::expected_status(&stderr, name);
+
+    lazy_static! {
+        static ref DONE: Regex = Regex::new(r"^Done (\d+) runs in").unwrap();
+        static ref CRASH: Regex = Regex::new(r"Test unit written to (\S+)").unwrap();
+    }
+
+    let mut stats = HashMap::new();
+    if let Some(runs) = stderr.lines().find_map(|l| DONE.captures(l)) {
+        stats.insert(String::from("runs"), runs[1].parse()?);
+    }
+
+    let messages: Vec<String> = stderr
+        .lines()
+        .chain(stdout.lines())
+        .filter(|l| {
+            l.contains("panicked at") || l.contains("FUZZ: ERROR") || l.contains("ERROR: libFuzzer")
+        })
+        .map(String::from)
+        .collect();
+
+    let crashes = stderr
+        .lines()
+        .filter_map(|l| CRASH.captures(l))
+        .map(|caps| PathBuf::from(&caps[1]))
+        .collect::<Vec<_>>();
+
+    let status = if stderr.contains("ERROR: libFuzzer: out-of-memory") {
+        Status::OutOfMemory
+    } else if stderr.contains("ERROR: libFuzzer: timeout") {
+        Status::Timeout
+    } else if stderr.contains("ERROR: libFuzzer:") {
+        if expect.is_some()
+            && stderr
+                .lines()
+                .any(|l| backends_common::is_expected_panic(l, &expect, name))
+        {
+            Status::Verified
+        } else {
+            backends_common::failure_status(&messages)
+        }
+    } else {
+        // The fuzzer did not find an input that makes the test fail, which
+        // does not mean that there is none: fuzzing can't verify a test.
+        if !stats.contains_key("runs") {
+            warn!("Unable to determine status of {}", name);
+        }
+        Status::Unknown
+    };
+    let status = backends_common::check_expected_status(&Fuzz, name, status, expected);
+
+    info!(
+        "Status: '{}' expected: '{}'",
+        status,
+        expect.unwrap_or("---")
+    );
+
+    Ok((status, stats, messages, crashes))
+}
+
+/// Replay an input that crashed the fuzz target.
+fn replay_fuzz(opt: &Opt, name: &str, input: &Path) -> CVResult<String> {
+    backends_common::replay(
+        opt,
+        name,
+        &backends_common::ReplayInput {
+            envs: vec![("RVT_FUZZ_INPUT", input.as_os_str())],
+            ..Default::default()
+        },
+    )
+}
@@ -6,26 +6,124 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::{collections::HashMap, ffi::OsString, fs, path::Path, process::Command};
-
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs, io,
+    path::Path,
+    process::Command,
+};
+
+use ktest::{ErrorKind, KTest, KleeError, TestCase};
 use lazy_static::lazy_static;
 use log::{info, warn};
 use regex::Regex;
 
-use crate::{utils::Append, *};
+use crate::{
+    counterexample::{self, Counterexample},
+    utils::Append,
+    *,
+};
+
+pub struct Klee;
+
+impl Backend for Klee {
+    fn name(&self) -> &'static str {
+        "klee"
+    }
+
+    /// Check if Klee is avilable.
+    fn check_install(&self, _opt: &Opt) -> bool {
+        let output = Command::new("which").arg("klee").output().ok();
+
+        match output {
+            Some(output) => output.status.success(),
+            None => false,
+        }
+    }
+
+    fn version(&self, opt: &Opt) -> CVResult<String> {
+        backends_common::tool_version(opt, "klee")
+    }
+
+    fn supports_replay(&self) -> bool {
+        true
+    }
+
+    fn supports_args(&self) -> bool {
+        true
+    }
+
+    fn supports_sym_args(&self) -> bool {
+        true
+    }
+
+    fn supports_sym_stdin(&self) -> bool {
+        true
+    }
+
+    fn supports_replay_sym_stdin(&self) -> bool {
+        true
+    }
+
+    fn supports_regression_tests(&self) -> bool {
+        true
+    }
+
+    fn supports_export_corpus(&self) -> bool {
+        true
+    }
+
+    fn supports_sym_files(&self) -> bool {
+        true
+    }
+
+    fn supports_coverage(&self) -> bool {
+        true
+    }
+
+    fn supports_profile(&self) -> bool {
+        true
+    }
+
+    fn supports_all_errors(&self) -> bool {
+        true
+    }
+
+    fn check_opt(&self, opt: &Opt) -> CVResult<()> {
+        if uses_posix_runtime(opt) && (opt.tests || !opt.test.is_empty()) {
+            // Symbolic arguments, standard input and files are created by
+            // KLEE's POSIX runtime, which wraps the C main function.
+            Err("'--sym-args', '--sym-stdin' and '--sym-files' can only be used when verifying 'main'.")?;
+        }
+        Ok(())
+    }
+
+    fn features(&self) -> &'static [&'static str] {
+        &["verifier-klee"]
+    }
+
+    fn rustflags(&self) -> &'static [&'static str] {
+        // Most of KLEE's verification API is also implemented in the
+        // kleeRuntest library (used when replaying tests) but klee_is_symbolic
+        // is not (and cannot be) provided in that library.
+        // Defining this symbol allows code that uses is_symbolic to be linked.
+        &["-Clink-arg=-Wl,--defsym=klee_is_symbolic=0"]
+    }
 
-/// Check if Klee is avilable.
-pub fn check_install() -> bool {
-    let output = Command::new("which").arg("klee").output().ok();
+    fn patch_llvm_options(&self) -> &'static [&'static str] {
+        // todo: This is probably useful with all verifiers - but
+        // making it KLEE-only until we have a chance to test it.
+        &["--initializers", "--features", "--intrinsics"]
+    }
 
-    match output {
-        Some(output) => output.status.success(),
-        None => false,
+    fn verify(&self, opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
+        verify(opt, name, entry, bcfile)
     }
 }
 
 /// Run Klee and replay
-pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Status> {
+fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
     // KLEE output files are put in kleeout directory with filename `name`
     let klee_dir = opt.cargo_toml.with_file_name("kleeout");
     fs::create_dir_all(&klee_dir)?;
@@ -46,7 +144,7 @@ pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Sta
     info!("      entry: {}", entry);
     info!("      results: {}", out_dir.to_string_lossy());
 
-    let (status, stats) = run(&opt, &name, &entry, &bcfile, &out_dir)?;
+    let (status, stats, messages) = run(opt, name, entry, bcfile, &out_dir)?;
     if !stats.is_empty() {
         match stats.get("completed paths") {
             Some(n) => info!("     {}: {} paths", name, n),
@@ -55,46 +153,264 @@ pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Sta
         info!("     {}: {:?}", name, stats);
     }
 
-    // {out_dir}/test*.err
-    let mut failures =
-        glob(&glob::Pattern::escape(out_dir.to_str().ok_or("not UTF-8")?).append("/test*.err"))?
-            .filter_map(Result::ok)
-            .collect::<Vec<_>>();
-    failures.sort_unstable();
-    info!("      Failing test: {:?}", failures);
+    // The test cases generated by KLEE, and the ones that failed.
+    let test_cases = if out_dir.is_dir() {
+        ktest::test_cases(&out_dir)?
+    } else {
+        Vec::new()
+    };
+    let failures = test_cases
+        .iter()
+        .filter(|t| t.error.is_some())
+        .collect::<Vec<_>>();
+    let failing_ktests = failures.iter().map(|t| t.ktest.clone()).collect::<Vec<_>>();
+    info!("      Failing test: {:?}", failing_ktests);
+
+    let mut result = TestResult::new(&Klee, name, entry, status);
+    result.stats = stats;
+    result.messages = messages;
+    result.output_dir = Some(out_dir.clone());
+
+    if status != Status::Verified {
+        if opt.all_errors {
+            result.findings = findings(&failures, entry)?;
+            // The status of the test is that of its worst error.
+            if let Some(worst) = result
+                .findings
+                .iter()
+                .map(|f| f.status)
+                .max_by_key(|s| s.severity())
+            {
+                result.status = worst;
+            }
+        }
+
+        // The location and backtrace are those of an error that has the
+        // status of the test (if KLEE found several errors).
+        let errors = failures
+            .iter()
+            .filter_map(|t| t.read_error().transpose())
+            .collect::<io::Result<Vec<_>>>()?;
+        if let Some(error) = errors
+            .iter()
+            .find(|error| error_status(error) == result.status)
+            .or_else(|| errors.first())
+        {
+            let (location, backtrace) = rust_backtrace(error, entry);
+            result.location = location;
+            result.backtrace = backtrace;
+        }
+    }
+
+    let mut counterexamples = failures
+        .iter()
+        .map(|t| {
+            let mut counterexample = Counterexample::new(&result, &t.ktest);
+            // Each test case has its own error (with `--all-errors`, there can
+            // be several kinds of errors).
+            if let Some(error) = t.read_error()? {
+                counterexample.status = error_status(&error);
+                let location = rust_backtrace(&error, entry).0;
+                if failures.len() > 1 || counterexample.panic.is_none() {
+                    counterexample.panic = location.or(counterexample.panic);
+                }
+            }
+            let ktest = t.read_ktest()?;
+            counterexample.values = ktest
+                .program_objects()
+                .iter()
+                .map(|o| counterexample::Value::from_object(&o.name, &o.bytes))
+                .collect();
+            counterexample.objects = ktest
+                .objects
+                .into_iter()
+                .map(|o| counterexample::Object {
+                    name:  o.name,
+                    bytes: o.bytes,
+                })
+                .collect();
+            Ok(counterexample)
+        })
+        .collect::<CVResult<Vec<_>>>()?;
 
     if opt.replay > 0 {
         // use -r -r to see all tests, not just failing tests
-        let mut ktests = if opt.replay > 1 {
-            // {out_dir}/test*.ktest
-            glob(
-                &glob::Pattern::escape(out_dir.to_str().ok_or("not UTF-8")?).append("/test*.ktest"),
-            )?
-            .filter_map(Result::ok)
-            .collect::<Vec<_>>()
+        let replayed = if opt.replay > 1 {
+            test_cases.iter().collect::<Vec<_>>()
         } else {
-            // Remove the '.err' extension and replace the '.*' ('.abort' or
-            // '.ptr') with '.ktest'.
             failures
-                .iter()
-                .map(|p| {
-                    p.with_extension("") // Remove '.err'
-                        .with_extension("ktest") // Replace '.*' with '.ktest'
-                })
-                .collect::<Vec<_>>()
         };
-        ktests.sort_unstable();
 
-        for ktest in ktests {
-            println!("    Test input {}", ktest.to_str().unwrap_or("???"));
-            match replay_klee(&opt, &name, &ktest) {
-                Ok(()) => (),
+        for t in replayed {
+            output!(&opt, "    Test input {}", t.ktest.to_str().unwrap_or("???"));
+            match replay_klee(opt, name, &t.ktest) {
+                Ok(output) => {
+                    if let Some(c) = counterexamples.iter_mut().find(|c| c.file == t.ktest) {
+                        c.add_replay_output(&output);
+                    }
+                }
                 Err(err) => warn!("Failed to replay: {}", err),
             }
         }
     }
 
-    Ok(status)
+    counterexample::save(opt, &result, &counterexamples)?;
+    // Expected failures (e.g., `should_panic`) make a regression test fail.
+    if result.status != Status::Verified {
+        regression::emit(opt, name, &counterexamples)?;
+    }
+    if let Some(corpus_dir) = &opt.export_corpus {
+        export_corpus(opt, name, &test_cases, corpus_dir)?;
+    }
+    coverage::report_test(opt, name, &out_dir)?;
+    if opt.profile {
+        profile(opt, name, &out_dir)?;
+    }
+
+    result.counterexamples = failing_ktests;
+    Ok(result)
+}
+
+/// Find the source location of a KLEE error in the Rust code, and a backtrace
+/// of the Rust functions that led to it (innermost first, ending with the test
+/// `entry`). The functions of the standard library and of the verification
+/// libraries (e.g., `verifier::assert!`) are left out: the location is in the
+/// innermost function of the crate (or of its other dependencies).
+fn rust_backtrace(error: &KleeError, entry: &str) -> (Option<String>, Vec<String>) {
+    let mut location = None;
+    let mut backtrace = Vec::new();
+    for frame in &error.stack {
+        let is_rust = frame.function.starts_with("_ZN") || frame.function.starts_with("_R");
+        let function = format!("{:#}", demangle(&frame.function));
+        let is_library = [
+            "core::",
+            "std::",
+            "alloc::",
+            "verification_annotations::",
+            "propverify::",
+        ]
+        .iter()
+        .any(|prefix| function.trim_start_matches('<').starts_with(prefix));
+        // The tests of the verification libraries are not library code.
+        if is_rust && (!is_library || frame.function == entry) {
+            if location.is_none() {
+                location = frame.location();
+            }
+            backtrace.push(match frame.location() {
+                Some(l) => format!("{} at {}", function, l),
+                None => function,
+            });
+        }
+        if frame.function == entry {
+            break;
+        }
+    }
+    (location.or_else(|| error.location()), backtrace)
+}
+
+/// The distinct errors (by kind and source location) of the failing test
+/// cases, each with the first test case that triggers it.
+fn findings(failures: &[&TestCase], entry: &str) -> CVResult<Vec<Finding>> {
+    let mut seen = HashSet::new();
+    let mut findings = Vec::new();
+    for t in failures {
+        let error = match t.read_error()? {
+            Some(error) => error,
+            None => continue,
+        };
+        let (location, _) = rust_backtrace(&error, entry);
+        if seen.insert((error.kind.to_string(), location.clone())) {
+            findings.push(Finding {
+                status: error_status(&error),
+                message: error.message.clone(),
+                location,
+                counterexample: t.ktest.clone(),
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// Classify a KLEE error. Rust panics are calls to abort, so they are
+/// classified by the panic functions on the stack.
+fn error_status(error: &KleeError) -> Status {
+    let panics_in = |f: &dyn Fn(&str) -> bool| {
+        error.stack.iter().any(|frame| {
+            let function = format!("{:#}", demangle(&frame.function));
+            function.starts_with("core::panicking::") && f(&function)
+        })
+    };
+    match error.kind {
+        ErrorKind::Overflow => Status::Overflow,
+        ErrorKind::Assert => Status::AssertFailed,
+        ErrorKind::Div => Status::DivisionByZero,
+        ErrorKind::Ptr if error.message.contains("out of bound pointer") => Status::OutOfBounds,
+        ErrorKind::Ptr | ErrorKind::Free => Status::InvalidPointer,
+        ErrorKind::Exec | ErrorKind::External | ErrorKind::Model => Status::Unsupported,
+        _ if error.message.contains("unreachable") => Status::Reachable,
+        ErrorKind::Abort if panics_in(&|f| f.contains("panic_bounds_check")) => Status::OutOfBounds,
+        ErrorKind::Abort if panics_in(&|f| f.contains("by_zero")) => Status::DivisionByZero,
+        ErrorKind::Abort if panics_in(&|f| f.contains("overflow")) => Status::Overflow,
+        ErrorKind::Abort if panics_in(&|f| f.contains("assert_failed")) => Status::AssertFailed,
+        ErrorKind::Abort if panics_in(&|_| true) => Status::Panic,
+        _ => Status::Error,
+    }
+}
+
+/// Convert the test cases (one per path explored by KLEE) into a fuzzing
+/// corpus in "{corpus_dir}/{name}": one file with the raw bytes of the symbolic
+/// objects per test case.
+fn export_corpus(
+    opt: &Opt,
+    name: &str,
+    test_cases: &[TestCase],
+    corpus_dir: &Path,
+) -> CVResult<()> {
+    let dir = corpus_dir.to_path_buf().append(name);
+    fs::create_dir_all(&dir)?;
+
+    info_at!(
+        &opt,
+        Verbosity::Informative,
+        "  Exporting {} inputs for {} to {}",
+        test_cases.len(),
+        name,
+        dir.to_string_lossy()
+    );
+
+    for t in test_cases {
+        let file = dir.clone().append(format!("test{:06}", t.id));
+        fs::write(&file, t.read_ktest()?.program_bytes())?;
+    }
+    Ok(())
+}
+
+/// Convert the instruction statistics in "{out_dir}/run.istats" into a
+/// kcachegrind profile "kleeout/callgrind.out.{name}" with demangled function
+/// names.
+fn profile(opt: &Opt, name: &str, out_dir: &Path) -> CVResult<()> {
+    let istats = out_dir.to_path_buf().append("run.istats");
+    if !istats.is_file() {
+        warn!("KLEE did not write a profile for {}", name);
+        return Ok(());
+    }
+    let callgrind = out_dir.with_file_name(format!("callgrind.out.{}", name));
+
+    Command::new("rust2calltree")
+        .arg(&istats)
+        .arg("-o")
+        .arg(&callgrind)
+        .args(vec!["-v"; opt.verbose])
+        .output_info(opt, Verbosity::Minor)
+        .map_err(|e| format!("Unable to run rust2calltree (is it installed?): {}", e))?;
+    info_at!(
+        &opt,
+        Verbosity::Informative,
+        "  Wrote profile of {} to {}",
+        name,
+        callgrind.to_string_lossy()
+    );
+    Ok(())
 }
 
 /// Return an int indicating importance of a line from KLEE's output
@@ -154,13 +470,14 @@ fn importance(line: &str, expect: &Option<&str>, name: &str) -> i8 {
 }
 
 /// Run Klee and analyse its output.
+/// Return the status, statistics and the lines of output that describe errors.
 fn run(
     opt: &Opt,
     name: &str,
     entry: &str,
     bcfile: &Path,
     out_dir: &Path,
-) -> CVResult<(Status, HashMap<String, isize>)> {
+) -> CVResult<(Status, HashMap<String, isize>, Vec<String>)> {
     let mut cmd = Command::new("klee");
 
     let user_flags: Vec<_> = opt
@@ -170,26 +487,44 @@ fn run(
         .collect::<Result<_, _>>()?;
 
     if !opt.replace_backend_flags {
+        if !opt.all_errors {
+            cmd.arg("--exit-on-error");
+        }
+        cmd.args(["--entry-point", entry]);
+        if uses_posix_runtime(opt) {
+            // The POSIX runtime (which creates the symbolic arguments,
+            // standard input and files) needs uclibc.
+            cmd.args(["--posix-runtime", "--libc=uclibc"]);
+        } else {
+            cmd.arg("--libc=klee");
+        }
         cmd.args(&[
-            "--exit-on-error",
-            "--entry-point",
-            entry,
-            // "--posix-runtime",
             // "--libcxx",
-            "--libc=klee",
             "--silent-klee-assume",
             "--disable-verify", // workaround https://github.com/klee/klee/issues/937
         ])
         .arg("--output-dir")
-        .arg(out_dir)
-        .args(user_flags)
-        .arg(bcfile)
-        .args(&opt.args);
+        .arg(out_dir);
+        if opt.profile {
+            // Make sure that the statistics are written, including the call
+            // graph.
+            cmd.args(["--output-stats", "--output-istats", "--use-call-paths"]);
+        }
+        cmd.args(user_flags).arg(bcfile).args(&opt.args);
+        if let [n, len] = opt.sym_args[..] {
+            cmd.args(["-sym-args", "0", &n.to_string(), &len.to_string()]);
+        }
+        if let [n, size] = opt.sym_files[..] {
+            cmd.args(["-sym-files", &n.to_string(), &size.to_string()]);
+        }
+        if let Some(size) = opt.sym_stdin {
+            cmd.args(["-sym-stdin", &size.to_string()]);
+        }
     } else {
         cmd.args(user_flags);
     }
 
-    let (_, stderr, _) = cmd.latin1_output_info_ignore_exit(&opt, Verbosity::Major)?;
+    let (_, stderr, _) = cmd.latin1_limited_output_info(opt, Verbosity::Major)?;
 
     // We scan stderr for:
     // 1. Indications of the expected output (eg from #[should_panic])
@@ -220,15 +555,11 @@ fn run(
             } else if l.starts_with("KLEE: halting execution, dumping remaining states") {
                 Some(Status::Timeout)
             } else if l.starts_with("KLEE: ERROR: Could not link") {
-                Some(Status::Unknown)
+                Some(Status::Unsupported)
             } else if l.starts_with("KLEE: ERROR: Unable to load symbol") {
-                Some(Status::Unknown)
-            } else if l.starts_with("KLEE: ERROR:") && l.contains("unreachable") {
-                Some(Status::Reachable)
-            } else if l.starts_with("KLEE: ERROR:") && l.contains("overflow") {
-                Some(Status::Overflow)
+                Some(Status::Unsupported)
             } else if l.starts_with("KLEE: ERROR:") {
-                Some(Status::Error)
+                Some(klee_error_status(l))
             } else if l.starts_with("VERIFIER_EXPECT:") {
                 // don't confuse this line with an error!
                 None
@@ -242,6 +573,8 @@ fn run(
                 Some(Status::OutOfBounds)
             } else if l.contains("with overflow") {
                 Some(Status::Overflow)
+            } else if l.contains("divide by zero") || l.contains("divisor of zero") {
+                Some(Status::DivisionByZero)
             } else if l.contains("panicked at") {
                 Some(Status::Panic)
             } else if l.contains("note: run with `RUST_BACKTRACE=1`") {
@@ -259,6 +592,12 @@ fn run(
             warn!("Unable to determine status of {}", name);
             Status::Unknown
         });
+    let status = backends_common::check_expected_status(
+        &Klee,
+        name,
+        status,
+        backends_common::expected_status(&stderr, name),
+    );
 
     info!("Status: '{}' expected: '{:?}'", status, expect);
 
@@ -285,50 +624,124 @@ fn run(
 
     for l in stderr.lines() {
         if importance(&l, &expect, &name) < opt.verbose as i8 {
-            println!("{}", l);
+            output!(&opt, "{}", l);
         }
     }
 
-    Ok((status, stats))
+    let messages = stderr
+        .lines()
+        .filter(|l| matches!(importance(l, &expect, name), -1 | 1 | 2))
+        .map(String::from)
+        .collect();
+
+    Ok((status, stats, messages))
 }
 
-/// Replay a KLEE "ktest" file
-fn replay_klee(opt: &Opt, name: &str, ktest: &Path) -> CVResult<()> {
-    let mut cmd = Command::new("cargo");
+/// Classify a "KLEE: ERROR: ..." message.
+fn klee_error_status(l: &str) -> Status {
+    if l.contains("unreachable") {
+        Status::Reachable
+    } else if l.contains("overflow") {
+        Status::Overflow
+    } else if l.contains("divide by zero") {
+        Status::DivisionByZero
+    } else if l.contains("out of bound pointer") {
+        Status::OutOfBounds
+    } else if ["null page", "null pointer", "free of", "invalid pointer"]
+        .iter()
+        .any(|m| l.contains(m))
+    {
+        Status::InvalidPointer
+    } else if l.contains("failed external call")
+        || l.contains("unsupported")
+        || l.contains("concretized symbolic size")
+    {
+        Status::Unsupported
+    } else {
+        Status::Error
+    }
+}
 
-    if opt.tests || !opt.test.is_empty() {
-        cmd.arg("test").arg("--manifest-path").arg(&opt.cargo_toml);
+/// Does KLEE need its POSIX runtime (to create symbolic arguments, standard
+/// input or files)?
+fn uses_posix_runtime(opt: &Opt) -> bool {
+    !opt.sym_args.is_empty() || opt.sym_stdin.is_some() || !opt.sym_files.is_empty()
+}
 
-        if !opt.features.is_empty() {
-            cmd.arg("--features").arg(opt.features.join(","));
-        }
+/// Replay a KLEE "ktest" file and return the output.
+fn replay_klee(opt: &Opt, name: &str, ktest: &Path) -> CVResult<String> {
+    if !uses_posix_runtime(opt) {
+        return backends_common::replay(
+            opt,
+            name,
+            &backends_common::ReplayInput {
+                envs: vec![("KTEST_FILE", ktest.as_os_str())],
+                ..Default::default()
+            },
+        );
+    }
 
-        cmd.arg(&name).args(&["--", "--nocapture"]);
-    } else {
-        cmd.arg("run").arg("--manifest-path").arg(&opt.cargo_toml);
+    // The symbolic arguments are passed on the command line, standard input
+    // and the symbolic files are written to files, and the objects created by
+    // the POSIX runtime must be removed from the ktest file (the kleeRuntest
+    // library expects only the objects created by the program).
+    let ktest_data = KTest::read(ktest)?;
+    let args = ktest_data.sym_args();
+    if !opt.sym_args.is_empty() {
+        output!(
+            &opt,
+            "    Arguments {:?}",
+            opt.args
+                .iter()
+                .map(OsString::from)
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>()
+        );
+    }
 
-        if !opt.features.is_empty() {
-            cmd.arg("--features").arg(opt.features.join(","));
+    let stdin = match ktest_data.stdin() {
+        Some(bytes) => {
+            output!(
+                &opt,
+                "    Standard input {:?}",
+                String::from_utf8_lossy(bytes)
+            );
+            let file = ktest.with_extension("stdin");
+            fs::write(&file, bytes)?;
+            Some(file)
         }
+        None => None,
+    };
 
-        if !opt.args.is_empty() {
-            cmd.arg("--").args(opt.args.iter());
+    let sym_files = ktest_data.sym_files();
+    let dir = if sym_files.is_empty() {
+        None
+    } else {
+        let dir = ktest.with_extension("files");
+        fs::create_dir_all(&dir)?;
+        for (file, bytes) in sym_files {
+            output!(
+                &opt,
+                "    File {} {:?}",
+                file,
+                String::from_utf8_lossy(bytes)
+            );
+            fs::write(dir.clone().append(file), bytes)?;
         }
-    }
-
-    let rustflags = match std::env::var_os("RUSTFLAGS") {
-        Some(env_rustflags) => env_rustflags.append(" --cfg=verify"),
-        None => OsString::from("--cfg=verify"),
+        Some(dir)
     };
-    cmd.env("RUSTFLAGS", rustflags).env("KTEST_FILE", ktest);
-
-    // Note that we do not treat this as an error, because
-    // the interesting case for replay is when KLEE had found an error.
-    let (stdout, stderr, _success) = cmd.output_info_ignore_exit(&opt, Verbosity::Major)?;
 
-    for line in stdout.lines().chain(stderr.lines()) {
-        println!("{}", line);
-    }
-
-    Ok(())
+    let native_ktest = ktest.with_extension("native-ktest");
+    ktest_data.without_posix_objects().write(&native_ktest)?;
+    backends_common::replay(
+        opt,
+        name,
+        &backends_common::ReplayInput {
+            args,
+            envs: vec![("KTEST_FILE", native_ktest.as_os_str())],
+            stdin,
+            dir,
+            ..Default::default()
+        },
+    )
 }
@@ -9,18 +9,22 @@
 #![feature(command_access)]
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     error, fmt,
     fs::{self, File, OpenOptions},
     io::{self, Write},
     path::{Path, PathBuf},
     process::{exit, Command},
     str::from_utf8,
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::Instant,
 };
 
 use ansi_term::Colour;
+use backend::Backend;
 use cargo_metadata::{CargoOpt, MetadataCommand};
 use glob::glob;
 use lazy_static::lazy_static;
@@ -28,6 +32,7 @@ use log::error;
 use rayon::prelude::*;
 use regex::Regex;
 use rustc_demangle::demangle;
+use serde::{Deserialize, Serialize};
 use structopt::{clap::arg_enum, StructOpt};
 use utils::{add_pre_ext, Append};
 
@@ -36,9 +41,18 @@ use utils::{add_pre_ext, Append};
 #[macro_use]
 mod utils;
 
+mod backend;
 mod backends_common;
+mod cache;
+mod counterexample;
+mod coverage;
+mod crux;
+mod fuzz;
 mod klee;
+mod portfolio;
 mod proptest;
+mod regression;
+mod report;
 mod run_tools;
 mod seahorn;
 mod smack;
@@ -68,23 +82,57 @@ pub struct Opt {
     #[structopt(value_name = "ARG", last = true)]
     args: Vec<String>,
 
+    /// Pass up to "N" symbolic arguments, each up to "LEN" characters long, to the program under test (after the arguments after '--')
+    #[structopt(long, value_names = &["N", "LEN"], number_of_values = 2)]
+    sym_args: Vec<usize>,
+
+    /// Make standard input symbolic, "SIZE" bytes long
+    #[structopt(long, value_name = "SIZE")]
+    sym_stdin: Option<usize>,
+
+    /// Create "N" symbolic files (named "A", "B", ...), each "SIZE" bytes long
+    #[structopt(long, value_names = &["N", "SIZE"], number_of_values = 2)]
+    sym_files: Vec<usize>,
+
     // backend_arg is used for holding the CL option. After parsing, if the user
     // specified a backend it will be copied to the `backend` field below, if
     // the user didn't specify a backend, we will auto-detect one, and put it
     // in the `backend` field.
-    /// Select verification backend
+    /// Select verification backend ("portfolio" runs all the installed backends that use LLVM bitcode, except "fuzz")
     #[structopt(
         short = "b",
         long = "backend",
         value_name = "BACKEND",
-        possible_values = &Backend::variants(),
+        possible_values = &backend::names(),
         case_insensitive = true,
     )]
-    backend_arg: Option<Backend>,
+    backend_arg: Option<String>,
 
     // See the comment of `backend_arg` above.
-    #[structopt(skip = Backend::Klee)] // the initial value has no meaning, it will be overwritten
-    backend: Backend,
+    #[structopt(skip = backend::BACKENDS[0])]
+    // the initial value has no meaning, it will be overwritten
+    backend: &'static dyn Backend,
+
+    // backends_arg is used for holding the CL option. After parsing, all the
+    // selected backends are put in the `backends` field below (this is just
+    // `backend` when not in portfolio mode).
+    /// Comma separated list of backends to run on every test, reporting the first conclusive result and any disagreement between them (portfolio mode)
+    #[structopt(
+        long = "backends",
+        value_name = "BACKENDS",
+        number_of_values = 1,
+        use_delimiter = true,
+        conflicts_with = "backend-arg"
+    )]
+    backends_arg: Vec<String>,
+
+    // See the comment of `backends_arg` above.
+    #[structopt(skip)]
+    backends: Vec<&'static dyn Backend>,
+
+    /// In portfolio mode, stop the other backends as soon as one of them reaches a conclusion (instead of checking that they agree)
+    #[structopt(long)]
+    stop_early: bool,
 
     /// Comma separated list of flags to pass to the verification backend ("{entry}" is replaced with the mangled entry function name; "{file}" is replaced with the LLVM-IR file; "{output_dir}" is replaced with the output directory), also see --replace-backend-flags
     #[structopt(long, value_name = "FLAGS", number_of_values = 1, use_delimiter = true)]
@@ -120,6 +168,18 @@ pub struct Opt {
     #[structopt(short, long)]
     clean: bool,
 
+    /// Always run the backends, instead of reusing results of unchanged tests from previous runs
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Remove all the cached results first
+    #[structopt(long)]
+    clear_cache: bool,
+
+    /// Show the counterexamples of "TEST" that were found by a previous run (instead of verifying); the types of the input values are only shown if the counterexamples were replayed
+    #[structopt(long, value_name = "TEST")]
+    show_counterexample: Option<String>,
+
     /// Build LLVM bitcode file and save to "PATH" instead of
     /// running verifier on it.
     #[structopt(short, long, value_name = "PATH", parse(from_os_str))]
@@ -150,6 +210,18 @@ pub struct Opt {
     #[structopt(skip)]
     jobs: usize,
 
+    /// Kill a verification backend that runs for more than "SECONDS" on a single test
+    #[structopt(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Kill a verification backend that uses more than "MB" megabytes of memory (Linux only)
+    #[structopt(long, value_name = "MB")]
+    memory_limit: Option<u64>,
+
+    /// Fuzz each test for "SECONDS" (fuzz backend only, 60 by default)
+    #[structopt(long, value_name = "SECONDS")]
+    fuzz_time: Option<u64>,
+
     /// Do not activate the `default` feature
     #[structopt(long)]
     no_default_features: bool,
@@ -158,6 +230,26 @@ pub struct Opt {
     #[structopt(short, long, parse(from_occurrences))]
     replay: usize,
 
+    /// Convert the counterexamples into regression tests (that don't need a verifier) in "DIR"
+    #[structopt(long, value_name = "DIR", parse(from_os_str))]
+    emit_regression_tests: Option<PathBuf>,
+
+    /// Export the inputs of all the paths explored by the backend as a fuzzing corpus (raw bytes, one file per input) in "DIR/TEST"
+    #[structopt(long, value_name = "DIR", parse(from_os_str))]
+    export_corpus: Option<PathBuf>,
+
+    /// Write lcov coverage reports for each test and for all tests (and an HTML report if 'genhtml' is installed) in "DIR"
+    #[structopt(long, value_name = "DIR", parse(from_os_str))]
+    coverage: Option<PathBuf>,
+
+    /// Keep going after the first error, and report each distinct error (by kind and source location) with its own counterexample
+    #[structopt(long)]
+    all_errors: bool,
+
+    /// Write a profile of the backend for each test that can be opened with kcachegrind (KLEE: "kleeout/callgrind.out.TEST", needs rust2calltree)
+    #[structopt(long)]
+    profile: bool,
+
     /// Use verbose output (-vvvvvv very verbose output)
     #[structopt(short, long, parse(from_occurrences))]
     verbose: usize,
@@ -166,6 +258,20 @@ pub struct Opt {
     #[structopt(short, long)]
     quiet: bool,
 
+    /// Format of the test results ("json" prints one JSON record per test, followed by a summary record)
+    #[structopt(
+        long,
+        value_name = "FMT",
+        possible_values = &MessageFormat::variants(),
+        case_insensitive = true,
+        default_value = "Human"
+    )]
+    message_format: MessageFormat,
+
+    /// Write a JUnit XML report of the test results to "PATH"
+    #[structopt(long, value_name = "PATH", parse(from_os_str))]
+    junit: Option<PathBuf>,
+
     // combined result of --verbose and --quiet options
     #[structopt(skip)]
     verbosity: Verbosity,
@@ -185,15 +291,14 @@ pub struct Opt {
 
 arg_enum! {
     #[derive(Debug, PartialEq, Copy, Clone)]
-    enum Backend {
-        Proptest,
-        Klee,
-        Seahorn,
-        Smack,
+    enum MessageFormat {
+        Human,
+        Json,
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Status {
     Unknown, // E.g. the verifier failed to execute.
     Verified,
@@ -204,6 +309,40 @@ pub enum Status {
     Panic,
     Reachable,
     Timeout,
+    OutOfMemory,
+    Inconsistent, // E.g. one backend verified a test and another found a violation.
+    DivisionByZero,
+    InvalidPointer, // A null or dangling pointer, or freeing invalid memory.
+    MemoryLeak,
+    Unsupported, // E.g. the test uses a feature that the verifier does not support.
+    BuildFailure,
+}
+
+impl Status {
+    /// How bad a status is, used to summarize the results of several tests
+    /// (the most severe status wins). Verifiers giving up is less severe than
+    /// any failure, and memory safety errors are the most severe failures.
+    /// A crate that does not build is worse than all of them.
+    fn severity(self) -> u8 {
+        match self {
+            Status::Verified => 0,
+            Status::Unknown => 1,
+            Status::Timeout => 2,
+            Status::OutOfMemory => 3,
+            Status::Unsupported => 4,
+            Status::Inconsistent => 5,
+            Status::Error => 6,
+            Status::Panic => 7,
+            Status::Reachable => 8,
+            Status::AssertFailed => 9,
+            Status::Overflow => 10,
+            Status::DivisionByZero => 11,
+            Status::OutOfBounds => 12,
+            Status::MemoryLeak => 13,
+            Status::InvalidPointer => 14,
+            Status::BuildFailure => 15,
+        }
+    }
 }
 
 impl fmt::Display for Status {
@@ -226,10 +365,84 @@ impl fmt::Display for Status {
             Status::Panic => write!(f, "PANIC"),
             Status::Reachable => write!(f, "REACHABLE"),
             Status::Timeout => write!(f, "TIMEOUT"),
+            Status::OutOfMemory => write!(f, "OUT_OF_MEMORY"),
+            Status::Inconsistent => write!(f, "INCONSISTENT"),
+            Status::DivisionByZero => write!(f, "DIVISION_BY_ZERO"),
+            Status::InvalidPointer => write!(f, "INVALID_POINTER"),
+            Status::MemoryLeak => write!(f, "MEMORY_LEAK"),
+            Status::Unsupported => write!(f, "UNSUPPORTED"),
+            Status::BuildFailure => write!(f, "BUILD_FAILURE"),
         }
     }
 }
 
+/// The result of running a verification backend on a single test.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    /// Name of the test (without the crate name)
+    name:            String,
+    /// The (mangled) entry function
+    entry:           String,
+    #[serde(skip_deserializing)]
+    backend:         &'static str,
+    status:          Status,
+    /// Wall-clock time spent running the backend, in seconds
+    time:            f32,
+    /// Statistics reported by the backend (e.g., KLEE's "completed paths")
+    stats:           HashMap<String, isize>,
+    /// Directory where the backend put its output files
+    output_dir:      Option<PathBuf>,
+    /// Files with concrete inputs that trigger a failure (e.g., KLEE's ktest files)
+    counterexamples: Vec<PathBuf>,
+    /// Short description of the failure (e.g., the panic message)
+    #[serde(default)]
+    message:         Option<String>,
+    /// Lines of the backend output that describe the failure
+    messages:        Vec<String>,
+    /// Source location of the failure ("file:line"), if known
+    #[serde(default)]
+    location:        Option<String>,
+    /// The Rust functions on the stack when the failure happened, innermost
+    /// first ("function at file:line")
+    #[serde(default)]
+    backtrace:       Vec<String>,
+    /// The distinct errors found (with `--all-errors`)
+    #[serde(default)]
+    findings:        Vec<Finding>,
+}
+
+impl TestResult {
+    fn new(backend: &dyn Backend, name: &str, entry: &str, status: Status) -> Self {
+        TestResult {
+            name: name.to_string(),
+            entry: entry.to_string(),
+            backend: backend.name(),
+            status,
+            time: 0.0,
+            stats: HashMap::new(),
+            output_dir: None,
+            counterexamples: Vec::new(),
+            message: None,
+            messages: Vec::new(),
+            location: None,
+            backtrace: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+}
+
+/// One of the distinct errors found in a test (with `--all-errors`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Finding {
+    status:         Status,
+    /// The backend's description of the error
+    message:        String,
+    /// Source location of the error ("file:line"), if known
+    location:       Option<String>,
+    /// File with a concrete input that triggers the error
+    counterexample: PathBuf,
+}
+
 type CVResult<T> = Result<T, Box<dyn error::Error>>;
 
 /// Verbosity levels
@@ -307,47 +520,79 @@ fn process_command_line() -> CVResult<Opt> {
         ));
     }
 
-    opt.backend = match opt.backend_arg {
-        // Check if the backend that was specified on the CL is installed.
-        Some(Backend::Proptest) => {
-            if opt.output.is_some() {
-                Err("backend proptest does not support --output")?;
-            }
-            assert!(proptest::check_install());
-            Backend::Proptest
-        }
-        Some(Backend::Klee) => {
-            if !klee::check_install() {
-                Err("Klee is not installed")?;
+    let portfolio =
+        matches!(&opt.backend_arg, Some(name) if name.eq_ignore_ascii_case(backend::PORTFOLIO));
+
+    opt.backends = if !opt.backends_arg.is_empty() {
+        // Check that all the backends specified on the CL are installed.
+        let mut backends: Vec<&'static dyn Backend> = Vec::new();
+        for name in &opt.backends_arg {
+            let backend =
+                backend::find(name).ok_or_else(|| format!("Unknown backend '{}'", name))?;
+            if !backend.uses_bitcode() {
+                Err(format!(
+                    "The {} backend can't be used with '--backends'",
+                    backend.name()
+                ))?;
             }
-            Backend::Klee
-        }
-        Some(Backend::Seahorn) => {
-            if !seahorn::check_install() {
-                Err("Seahorn is not installed")?;
+            if !backend.check_install(&opt) {
+                Err(format!("{} is not installed", backend.name()))?;
             }
-            Backend::Seahorn
-        }
-        Some(Backend::Smack) => {
-            if !smack::check_install() {
-                Err("SMACK is not installed")?;
+            if !backends.iter().any(|b| b.name() == backend.name()) {
+                backends.push(backend);
             }
-            Backend::Smack
         }
-        None => {
-            // If the user did not specify a backend, use the first one that we find.
-            let backend = if klee::check_install() {
-                Backend::Klee
-            } else if seahorn::check_install() {
-                Backend::Seahorn
-            } else {
-                assert!(proptest::check_install());
-                Backend::Proptest
-            };
-            info_at!(&opt, Verbosity::Normal, "Using {} as backend", backend);
-            backend
+        backends
+    } else if portfolio {
+        // Use all the installed backends that can verify bitcode (but not
+        // fuzz, which can only find failures).
+        let backends: Vec<&'static dyn Backend> = backend::BACKENDS
+            .iter()
+            .copied()
+            .filter(|b| b.uses_bitcode() && b.is_complete() && b.check_install(&opt))
+            .collect();
+        if backends.is_empty() {
+            Err("No backend is installed")?;
         }
+        info_at!(
+            &opt,
+            Verbosity::Normal,
+            "Using {} as backends",
+            backends
+                .iter()
+                .map(|b| b.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        backends
+    } else {
+        vec![match &opt.backend_arg {
+            // Check if the backend that was specified on the CL is installed.
+            Some(name) => {
+                let backend = backend::find(name).ok_or("Unknown backend")?;
+                if !backend.check_install(&opt) {
+                    Err(format!("{} is not installed", backend.name()))?;
+                }
+                backend
+            }
+            None => {
+                // If the user did not specify a backend, use the first one that we find.
+                let backend = backend::BACKENDS
+                    .iter()
+                    .copied()
+                    .find(|b| b.check_install(&opt))
+                    .ok_or("No backend is installed")?;
+                info_at!(
+                    &opt,
+                    Verbosity::Normal,
+                    "Using {} as backend",
+                    backend.name()
+                );
+                backend
+            }
+        }]
     };
+    opt.backend = opt.backends[0];
 
     // To be compatible with `cargo test`, features might be space separated.
     opt.features = opt
@@ -358,35 +603,25 @@ fn process_command_line() -> CVResult<Opt> {
         .collect::<Vec<_>>();
 
     // Backend specific options.
-    match opt.backend {
-        Backend::Proptest => {
-            if opt.replay > 0 && !opt.args.is_empty() {
-                Err("The Proptest backend does not support '--replay' and passing arguments together.")?;
-            }
+    for backend in &opt.backends {
+        backend::validate_opt(*backend, &opt)?;
+    }
+    if opt.backends.len() > 1 {
+        // Portfolio mode: each backend is built separately, with its own
+        // features (see `build_features`).
+        if opt.output.is_some() {
+            Err("The portfolio mode does not support '--output'.")?;
         }
-        Backend::Seahorn => {
-            if !opt.args.is_empty() {
-                Err("The Seahorn backend does not support passing arguments yet.")?;
-            }
-            if opt.replay != 0 {
-                Err("The Seahorn backend does not support '--replay' yet.")?;
-            }
-
-            opt.features.push(String::from("verifier-seahorn"));
+        if opt.replay != 0 {
+            Err("The portfolio mode does not support '--replay'.")?;
         }
-        Backend::Smack => {
-            if !opt.args.is_empty() {
-                Err("The SMACK backend does not support passing arguments yet.")?;
-            }
-            if opt.replay != 0 {
-                Err("The SMACK backend does not support '--replay' yet.")?;
-            }
+    } else {
+        opt.features
+            .extend(opt.backend.features().iter().map(|f| String::from(*f)));
+    }
 
-            opt.features.push(String::from("verifier-smack"));
-        }
-        Backend::Klee => {
-            opt.features.push(String::from("verifier-klee"));
-        }
+    if opt.memory_limit.is_some() && !cfg!(target_os = "linux") {
+        Err("The '--memory-limit' option is only supported on Linux.")?;
     }
 
     // Use the user specified number of jobs, or the number of CPUs.
@@ -400,9 +635,16 @@ fn main() -> CVResult<()> {
     let opt = process_command_line()?;
     stderrlog::new().verbosity(opt.verbose).init()?;
 
+    if let Some(name) = &opt.show_counterexample {
+        return counterexample::show(&opt, name);
+    }
+
     if opt.clean {
         clean(&opt);
     }
+    if opt.clear_cache {
+        cache::clear(&opt)?;
+    }
 
     let package = match &opt.bin {
         Some(bin) => bin.clone(),
@@ -410,27 +652,27 @@ fn main() -> CVResult<()> {
     };
     info_at!(&opt, Verbosity::Informative, "Checking {}", &package);
 
-    let status = match opt.backend {
-        Backend::Proptest => {
-            info_at!(
-                &opt,
-                Verbosity::Informative,
-                "  Invoking cargo run with proptest backend"
-            );
-            proptest::run(&opt)
-        }
-        _ => {
-            let target = get_default_host(&opt)?;
-            info_at!(&opt, Verbosity::Trivial, "target: {}", target);
-            verify(&opt, &package, &target)
-        }
+    let status = if opt.backend.uses_bitcode() {
+        let target = get_default_host(&opt)?;
+        info_at!(&opt, Verbosity::Trivial, "target: {}", target);
+        verify(&opt, &package, &target)
+    } else {
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  Invoking the {} backend",
+            opt.backend.name()
+        );
+        opt.backend.run(&opt, &package)
     }
     .unwrap_or_else(|err| {
         error!("{}", err);
         exit(1)
     });
 
-    println!("VERIFICATION_RESULT: {}", status);
+    if opt.message_format == MessageFormat::Human {
+        println!("VERIFICATION_RESULT: {}", status);
+    }
     if status != Status::Verified {
         exit(1);
     }
@@ -438,49 +680,20 @@ fn main() -> CVResult<()> {
 }
 
 /// Compile a Rust crate to generate bitcode and run one of the LLVM verifier
-/// backends on the result.
+/// backends (or several of them, in portfolio mode) on the result.
 fn verify(opt: &Opt, package: &str, target: &str) -> CVResult<Status> {
     let beginning = Instant::now();
 
-    // Compile and link the patched file using LTO to generate the entire
-    // application in a single LLVM file
-    info_at!(
-        &opt,
-        Verbosity::Informative,
-        "  Building {} for verification",
-        package
-    );
-    let bcfile = build(&opt, &package, &target)?;
-
-    info_at!(
-        &opt,
-        Verbosity::Informative,
-        "  Generated LLVM bitcode file {}",
-        bcfile.to_string_lossy()
-    );
-
-    if let Some(output) = &opt.output {
-        std::fs::copy(bcfile, output)?;
-        info_at!(
-            &opt,
-            Verbosity::Informative,
-            "Wrote LLVM bitcode file to {}",
-            output.to_string_lossy()
-        );
-        exit(0) // return immediately, do not print status
-    }
-
-    // Get the functions we need to verify, and their mangled names.
-    let tests = if opt.tests || !opt.test.is_empty() {
-        // If using the --tests or --test flags, generate a list of tests and
-        // their mangled names.
+    // Get the names of the tests we need to verify (None for 'main').
+    let names = if opt.tests || !opt.test.is_empty() {
+        // If using the --tests or --test flags, generate a list of tests.
         info_at!(
             &opt,
             Verbosity::Minor,
             "  Getting list of tests in {}",
             &package
         );
-        let mut tests = list_tests(&opt, &target)?;
+        let mut tests = list_tests(opt, opt.backend, target)?;
         if !opt.test.is_empty() {
             tests = tests
                 .into_iter()
@@ -490,50 +703,112 @@ fn verify(opt: &Opt, package: &str, target: &str) -> CVResult<Status> {
         if tests.is_empty() {
             Err("  No tests found")?
         }
-        let tests: Vec<String> = tests
-            .iter()
-            .map(|t| format!("{}::{}", package, t))
-            .collect();
-
-        // then look up their mangled names in the bcfile
-        mangle_functions(&opt, &bcfile, &tests)?
-    } else if opt.backend == Backend::Seahorn {
-        // Find the entry function (mangled main)
-        let mains = mangle_functions(&opt, &bcfile, &[String::from(package) + "::main"])?;
-        match mains.as_slice() {
-            [(_, _)] => mains,
-            [] => Err("  FAILED: can't find the 'main' function")?,
-            _ => Err("  FAILED: found more than one 'main' function")?,
-        }
+        Some(tests)
     } else {
-        vec![("main".to_string(), "main".to_string())]
+        None
     };
 
-    // Remove the package name from the function names (important for Klee?) in tests.
-    let tests: Vec<_> = tests
-        .into_iter()
-        .map(|(name, mangled)| {
-            if let Some(name) = name.strip_prefix(&format!("{}::", package)) {
-                (name.to_string(), mangled)
-            } else {
-                (name, mangled)
+    // Build the crate for each backend, and find the entry functions of the
+    // tests in the resulting bitcode (the mangled names depend on the
+    // features, so they can be different for each backend).
+    let mut builds: Vec<Build> = Vec::new();
+    for backend in &opt.backends {
+        // Compile and link the patched file using LTO to generate the entire
+        // application in a single LLVM file
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  Building {} for verification with {}",
+            package,
+            backend.name()
+        );
+        let bcfile = match build(opt, *backend, package, target) {
+            Ok(bcfile) => bcfile,
+            Err(err) => {
+                error!("{}", err);
+                error!("Failed to build {} for {}.", package, backend.name());
+                if opt.message_format == MessageFormat::Json {
+                    report::json_summary(
+                        Status::BuildFailure,
+                        None,
+                        &[],
+                        beginning.elapsed().as_secs_f32(),
+                    )?;
+                }
+                return Ok(Status::BuildFailure);
             }
+        };
+
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  Generated LLVM bitcode file {}",
+            bcfile.to_string_lossy()
+        );
+
+        if let Some(output) = &opt.output {
+            std::fs::copy(bcfile, output)?;
+            info_at!(
+                &opt,
+                Verbosity::Informative,
+                "Wrote LLVM bitcode file to {}",
+                output.to_string_lossy()
+            );
+            exit(0) // return immediately, do not print status
+        }
+
+        let entries = find_entries(opt, *backend, package, &bcfile, &names)?;
+        // Replaying, regression tests, exporting the corpus, coverage reports
+        // and profiles need the output of the backend.
+        let cache = if opt.no_cache
+            || opt.replay > 0
+            || opt.emit_regression_tests.is_some()
+            || opt.export_corpus.is_some()
+            || opt.coverage.is_some()
+            || opt.profile
+            || !backend.is_complete()
+        {
+            None
+        } else {
+            Some(cache::Cache::new(opt, *backend, &bcfile)?)
+        };
+        builds.push(Build {
+            backend: *backend,
+            bcfile,
+            entries,
+            cache,
+        });
+    }
+
+    // The tests, in the order they were listed, and how to run each one of
+    // them with each backend (None if the test is not in the build for that
+    // backend, e.g., because it depends on the backend's features).
+    let tests: Vec<(String, Vec<Run>)> = names
+        .unwrap_or_else(|| vec![String::from("main")])
+        .into_iter()
+        .map(|name| {
+            let runs = builds
+                .iter()
+                .map(|build| (build, build.entries.get(&name).map(String::as_str)))
+                .collect();
+            (name, runs)
         })
         .collect();
 
     #[rustfmt::skip]
     info_at!(&opt, Verbosity::Informative, "  Checking {}",
-             tests.iter().cloned().unzip::<_, _, Vec<_>, Vec<_>>().0.join(", ")
+             tests.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ")
     );
-    info_at!(opt, Verbosity::Trivial, "Mangled: {:?}", tests);
 
-    // For each test function, we run the backend and sift through its
+    // For each test function, we run the backends and sift through their
     // output to generate an appropriate status string.
-    println!("Running {} test(s)", tests.len());
+    if opt.message_format == MessageFormat::Human {
+        println!("Running {} test(s)", tests.len());
+    }
 
     let before_verifier = Instant::now();
 
-    let results: Vec<Status> = if opt.jobs > 1 {
+    let results: Vec<TestResult> = if opt.jobs > 1 {
         // Run the verification in parallel.
 
         // `build_global` must not be called more than once!
@@ -544,32 +819,26 @@ fn verify(opt: &Opt, package: &str, target: &str) -> CVResult<Status> {
 
         tests
             .par_iter() // <- parallelised iterator
-            .map(|(name, entry)| verifier_run(&opt, &bcfile, &name, &entry))
+            .map(|(name, runs)| verifier_run(opt, name, runs))
             .collect()
     } else {
         // Same as above but without the overhead of rayon
         tests
             .iter() // <- this is the only difference
-            .map(|(name, entry)| verifier_run(&opt, &bcfile, &name, &entry))
+            .map(|(name, runs)| verifier_run(opt, name, runs))
             .collect()
     };
 
-    // Count pass/fail
-    let passes = results.iter().filter(|r| **r == Status::Verified).count();
-    let fails = results.len() - passes;
-    // randomly pick one failing status (if any)
-    let status = results
-        .into_iter()
-        .find(|r| *r != Status::Verified)
-        .unwrap_or(Status::Verified);
+    coverage::report_all(opt, &results)?;
 
     let end = Instant::now();
 
-    // Note use of \n to end line of results in --quiet mode
-    println!(
-        "\ntest result: {:#}. {} passed; {} failed",
-        status, passes, fails
-    );
+    let status = report_results(
+        opt,
+        package,
+        &results,
+        end.duration_since(beginning).as_secs_f32(),
+    )?;
 
     info_at!(
         &opt,
@@ -593,24 +862,178 @@ fn verify(opt: &Opt, package: &str, target: &str) -> CVResult<Status> {
     Ok(status)
 }
 
-/// Invoke one of the supported verification backends on entry point 'entry'
-/// (with pretty name 'name') in bitcodefile 'bcfile'.
-fn verifier_run(opt: &Opt, bcfile: &Path, name: &str, entry: &str) -> Status {
-    let status = match opt.backend {
-        Backend::Klee => klee::verify(&opt, &name, &entry, &bcfile),
-        Backend::Seahorn => seahorn::verify(&opt, &name, &entry, &bcfile),
-        Backend::Smack => smack::verify(&opt, &name, &entry, &bcfile),
-        Backend::Proptest => unreachable!(),
+/// Find the entry functions of the tests `names` (or of 'main' if `names` is
+/// None) in `bcfile`. Return a map from the test names (without the package
+/// name) to their mangled names. The tests that are not in `bcfile` are not in
+/// the map.
+fn find_entries(
+    opt: &Opt,
+    backend: &dyn Backend,
+    package: &str,
+    bcfile: &Path,
+    names: &Option<Vec<String>>,
+) -> CVResult<HashMap<String, String>> {
+    let entries = if let Some(names) = names {
+        let names: Vec<String> = names
+            .iter()
+            .map(|t| format!("{}::{}", package, t))
+            .collect();
+
+        // Look up their mangled names in the bcfile
+        mangle_functions(opt, bcfile, &names)?
+    } else if backend.needs_mangled_main() {
+        // Find the entry function (mangled main)
+        let mains = mangle_functions(opt, bcfile, &[String::from(package) + "::main"])?;
+        match mains.as_slice() {
+            [(_, mangled)] => vec![(String::from("main"), mangled.clone())],
+            [] => Err("  FAILED: can't find the 'main' function")?,
+            _ => Err("  FAILED: found more than one 'main' function")?,
+        }
+    } else {
+        vec![("main".to_string(), "main".to_string())]
+    };
+
+    // Remove the package name from the function names (important for Klee?) in tests.
+    let entries: HashMap<String, String> = entries
+        .into_iter()
+        .map(|(name, mangled)| {
+            if let Some(name) = name.strip_prefix(&format!("{}::", package)) {
+                (name.to_string(), mangled)
+            } else {
+                (name, mangled)
+            }
+        })
+        .collect();
+
+    info_at!(opt, Verbosity::Trivial, "Mangled: {:?}", entries);
+    Ok(entries)
+}
+
+/// The crate, built for a specific backend.
+struct Build {
+    backend: &'static dyn Backend,
+    bcfile:  PathBuf,
+    /// Map from test names to their entry functions in `bcfile`
+    entries: HashMap<String, String>,
+    /// Results of previous runs (None when caching is disabled)
+    cache:   Option<cache::Cache>,
+}
+
+/// A build of the crate and the entry function of a test in that build (None
+/// if the test is not in the build).
+type Run<'a> = (&'a Build, Option<&'a str>);
+
+/// Run the backends on the test `name`, and report the result. `runs` holds
+/// the build and entry function for each backend.
+///
+/// In portfolio mode, all the backends run to completion, so that their
+/// results can be checked against each other, unless `--stop-early` is given:
+/// then the backends that have not finished when one of them reaches a
+/// conclusion are stopped (or not started).
+fn verifier_run(opt: &Opt, name: &str, runs: &[Run]) -> TestResult {
+    let stop_early = opt.stop_early && runs.len() > 1;
+    let stop = Arc::new(AtomicBool::new(false));
+    let run = |(build, entry): &Run| {
+        if stop.load(Ordering::SeqCst) {
+            return None;
+        }
+        let result = match entry {
+            Some(entry) if stop_early => {
+                run_tools::stop_when(&stop, || backend_run(opt, build, name, entry))
+            }
+            Some(entry) => backend_run(opt, build, name, entry),
+            None => {
+                let mut result = TestResult::new(build.backend, name, "", Status::Unsupported);
+                result.message = Some(format!(
+                    "{} was not found in the build for {}",
+                    name,
+                    build.backend.name()
+                ));
+                result
+            }
+        };
+        if stop_early && portfolio::is_conclusive(&result) {
+            stop.store(true, Ordering::SeqCst);
+        }
+        Some(result)
+    };
+    let results: Vec<TestResult> = if opt.jobs > 1 && runs.len() > 1 {
+        runs.par_iter().filter_map(run).collect()
+    } else {
+        runs.iter().filter_map(run).collect()
+    };
+    let result = portfolio::combine(opt, results);
+
+    report_test(opt, &result);
+    result
+}
+
+/// Run a single backend on the test `name`, or reuse the result of a previous
+/// run if nothing changed since then.
+fn backend_run(opt: &Opt, build: &Build, name: &str, entry: &str) -> TestResult {
+    let backend = build.backend;
+
+    if let Some(result) = build.cache.as_ref().and_then(|c| c.lookup(backend, entry)) {
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  {}: using the cached {} result",
+            name,
+            backend.name()
+        );
+        return result;
     }
-    .unwrap_or_else(|err| {
-        error!("{}", err);
-        error!("Failed to run test '{}'.", name);
-        Status::Unknown
-    });
 
+    let beginning = Instant::now();
+
+    let mut result = match backend.verify(opt, name, entry, &build.bcfile) {
+        Ok(result) => result,
+        // Another backend reached a conclusion first (with `--stop-early`):
+        // the result must not be cached.
+        Err(err) if err.is::<Stopped>() => {
+            return TestResult::new(backend, name, entry, Status::Unknown);
+        }
+        Err(err) => match err.downcast_ref::<LimitExceeded>() {
+            Some(exceeded) => {
+                // A test can be expected to time out (see `verifier::expect_status`).
+                let expected = backends_common::expected_status(&exceeded.output, name);
+                let status = backends_common::check_expected_status(
+                    backend,
+                    name,
+                    exceeded.status(),
+                    expected,
+                );
+                let mut result = TestResult::new(backend, name, entry, status);
+                result.messages.push(format!("{}: {}", name, exceeded));
+                result
+            }
+            None => {
+                error!("{}", err);
+                error!("Failed to run test '{}'.", name);
+                TestResult::new(backend, name, entry, Status::Unknown)
+            }
+        },
+    };
+    result.time = beginning.elapsed().as_secs_f32();
+    if result.status != Status::Verified && result.message.is_none() {
+        result.message = result.messages.first().cloned();
+    }
+
+    if let Some(cache) = &build.cache {
+        cache
+            .store(&result)
+            .unwrap_or_else(|err| error!("Failed to cache the result of '{}': {}", name, err));
+    }
+    result
+}
+
+/// Print the result of a single test (in the format selected by the options).
+fn report_test(opt: &Opt, result: &TestResult) {
     let mut stdout = io::stdout();
-    if opt.quiet {
-        let s = match status {
+    if opt.message_format == MessageFormat::Json {
+        report::json_test(result).unwrap_or_else(|err| error!("{}", err));
+    } else if opt.quiet {
+        let s = match result.status {
             Status::Unknown => Colour::Yellow.paint("?"),
             Status::Verified => Colour::Green.paint("."),
             Status::Error => Colour::Red.paint("F"),
@@ -620,18 +1043,103 @@ fn verifier_run(opt: &Opt, bcfile: &Path, name: &str, entry: &str) -> Status {
             Status::Panic => Colour::Red.paint("P"),
             Status::Reachable => Colour::Red.paint("R"),
             Status::Timeout => Colour::Red.paint("T"),
+            Status::OutOfMemory => Colour::Red.paint("M"),
+            Status::Inconsistent => Colour::Red.paint("I"),
+            Status::DivisionByZero => Colour::Red.paint("D"),
+            Status::InvalidPointer => Colour::Red.paint("N"),
+            Status::MemoryLeak => Colour::Red.paint("L"),
+            Status::Unsupported => Colour::Yellow.paint("U"),
+            Status::BuildFailure => Colour::Red.paint("X"),
         };
         write!(stdout, "{}", s).unwrap();
     } else {
-        writeln!(stdout, "test {} ... {:#}", name, status).unwrap();
+        writeln!(stdout, "test {} ... {:#}", result.name, result.status).unwrap();
+        if result.findings.is_empty() {
+            if let Some(location) = &result.location {
+                writeln!(stdout, "    at {}", location).unwrap();
+            }
+        }
+        for finding in &result.findings {
+            writeln!(
+                stdout,
+                "    {:#} at {} ({})",
+                finding.status,
+                finding.location.as_deref().unwrap_or("unknown location"),
+                finding.counterexample.to_string_lossy()
+            )
+            .unwrap();
+        }
+        if opt.verbosity >= Verbosity::Informative {
+            for frame in &result.backtrace {
+                writeln!(stdout, "      {}", frame).unwrap();
+            }
+        }
     }
     stdout.flush().unwrap();
-    status
+}
+
+/// Print the summary of the test results, and write the JUnit report if
+/// requested. `time` is the total time in seconds. Returns the overall status.
+fn report_results(opt: &Opt, package: &str, results: &[TestResult], time: f32) -> CVResult<Status> {
+    // Count pass/fail
+    let passes = results
+        .iter()
+        .filter(|r| r.status == Status::Verified)
+        .count();
+    let fails = results.len() - passes;
+    // The number of failing tests with each status, most severe first.
+    let mut counts: Vec<(Status, usize)> = Vec::new();
+    for result in results.iter().filter(|r| r.status != Status::Verified) {
+        match counts.iter_mut().find(|(s, _)| *s == result.status) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((result.status, 1)),
+        }
+    }
+    counts.sort_by_key(|(s, _)| std::cmp::Reverse(s.severity()));
+    // The most severe failing status (if any)
+    let status = counts.first().map_or(Status::Verified, |(s, _)| *s);
+
+    if let Some(junit) = &opt.junit {
+        report::write_junit(junit, package, results)?;
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "Wrote JUnit report to {}",
+            junit.to_string_lossy()
+        );
+    }
+
+    match opt.message_format {
+        MessageFormat::Human => {
+            let categories = if counts.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " ({})",
+                    counts
+                        .iter()
+                        .map(|(s, n)| format!("{} {}", n, s))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            // Note use of \n to end line of results in --quiet mode
+            println!(
+                "\ntest result: {:#}. {} passed; {} failed{}",
+                status, passes, fails, categories
+            );
+        }
+        MessageFormat::Json => {
+            report::json_summary(status, Some((passes, fails)), &counts, time)?;
+        }
+    }
+
+    Ok(status)
 }
 
 /// Compile, link and do transformations on LLVM bitcode.
-fn build(opt: &Opt, package: &str, target: &str) -> CVResult<PathBuf> {
-    let (mut bc_file, c_files) = compile(&opt, &package, target)?;
+fn build(opt: &Opt, backend: &dyn Backend, package: &str, target: &str) -> CVResult<PathBuf> {
+    let (mut bc_file, c_files) = compile(opt, backend, package, target)?;
 
     // Link bc file (from all the Rust code) against the (backend-specific)
     // runtime library and any c_files generated by build scripts from any C/C++
@@ -642,7 +1150,7 @@ fn build(opt: &Opt, package: &str, target: &str) -> CVResult<PathBuf> {
     let runtime = rvt_dir
         .clone()
         .append("runtime")
-        .append(format!("rvt-{}.bc", opt.backend.to_string().to_lowercase()));
+        .append(format!("rvt-{}.bc", backend.name()));
     let simd_emulation = rvt_dir
         .clone()
         .append("simd_emulation")
@@ -672,45 +1180,26 @@ fn build(opt: &Opt, package: &str, target: &str) -> CVResult<PathBuf> {
         .latin1_output_info(&opt, Verbosity::Major)?;
     bc_file = new_bc_file;
 
-    if opt.backend == Backend::Seahorn {
-        info_at!(&opt, Verbosity::Major, "  Patching LLVM file for Seahorn");
-        let new_bc_file = add_pre_ext(&bc_file, "patch-sea");
-        patch_llvm(&opt, &["--seahorn"], &bc_file, &new_bc_file)?;
-        bc_file = new_bc_file;
-    }
-
-    if opt.backend == Backend::Smack {
-        info_at!(&opt, Verbosity::Major, "  Patching LLVM file for Smack");
-        let new_bc_file = add_pre_ext(&bc_file, "patch-smack");
-        patch_llvm(&opt, &["--smack"], &bc_file, &new_bc_file)?;
-        bc_file = new_bc_file;
-    }
-
-    // todo: This is probably useful with all verifiers - but
-    // making it KLEE-only until we have a chance to test it.
-    if opt.backend == Backend::Klee {
+    let patch_options = backend.patch_llvm_options();
+    if !patch_options.is_empty() {
         info_at!(
             &opt,
             Verbosity::Major,
-            "  Patching LLVM file for initializers, feature tests, and SIMD"
+            "  Patching LLVM file for {}",
+            backend.name()
         );
-        let new_bc_file = add_pre_ext(&bc_file, "patch-init-feat");
-        patch_llvm(
-            &opt,
-            &["--initializers", "--features", "--intrinsics"],
-            &bc_file,
-            &new_bc_file,
-        )?;
+        let new_bc_file = add_pre_ext(&bc_file, format!("patch-{}", backend.name()));
+        patch_llvm(opt, patch_options, &bc_file, &new_bc_file)?;
         bc_file = new_bc_file;
     }
 
     Ok(bc_file)
 }
 
-/// Return the environment variables needed for building.  Each item in the
-/// vector is a pair `(a, b)` where `a` is the variable name and `b` is its
-/// value.
-fn get_build_envs(opt: &Opt) -> CVResult<Vec<(String, String)>> {
+/// Return the environment variables needed for building for `backend`.  Each
+/// item in the vector is a pair `(a, b)` where `a` is the variable name and `b`
+/// is its value.
+fn get_build_envs(opt: &Opt, backend: &dyn Backend) -> CVResult<Vec<(String, String)>> {
     let mut rustflags = vec![
         "-Clto", // Generate linked bitcode for entire crate
         "-Cembed-bitcode=yes",
@@ -731,20 +1220,9 @@ fn get_build_envs(opt: &Opt) -> CVResult<Vec<(String, String)>> {
     ]
     .join(" ");
 
-    if opt.backend == Backend::Klee {
-        // Most of KLEE's verification API is also implemented in the
-        // kleeRuntest library (used when replaying tests) but klee_is_symbolic
-        // is not (and cannot be) provided in that library.
-        // Defining this symbol allows code that uses is_symbolic to be linked.
-        rustflags.push_str(" -Clink-arg=-Wl,--defsym=klee_is_symbolic=0");
-    }
-
-    if opt.backend == Backend::Smack {
-        // Flags for compiling for smack
-        rustflags.push_str(" -Copt-level=0");
-        rustflags.push_str(" -Cno-prepopulate-passes");
-        rustflags.push_str(" -g");
-        rustflags.push_str(" -Cpasses=name-anon-globals");
+    for flag in backend.rustflags() {
+        rustflags.push(' ');
+        rustflags.push_str(flag);
     }
 
     match std::env::var_os("RUSTFLAGS") {
@@ -763,15 +1241,54 @@ fn get_build_envs(opt: &Opt) -> CVResult<Vec<(String, String)>> {
     ])
 }
 
-/// Compile a crate for verification.
+/// The features to activate when building the crate for `backend`.
+fn build_features(opt: &Opt, backend: &dyn Backend) -> Vec<String> {
+    let mut features = opt.features.clone();
+    for f in backend.features() {
+        if !features.iter().any(|g| g == f) {
+            features.push(String::from(*f));
+        }
+    }
+    features
+}
+
+/// The target directory used when building the crate for `backend`.
+/// In portfolio mode, each backend uses different features and flags, so it
+/// gets its own target directory to avoid rebuilding (and mixing up bitcode
+/// files) every time.
+fn build_target_directory(opt: &Opt, backend: &dyn Backend) -> CVResult<PathBuf> {
+    let target_dir = get_meta_target_directory(opt)?;
+    if opt.backends.len() > 1 {
+        Ok(target_dir.append(format!("verify-{}", backend.name())))
+    } else {
+        Ok(target_dir)
+    }
+}
+
+/// Compile a crate for verification with `backend`.
 /// Return a bcfile for the entire (linked) crate, and c object files that need
 /// to be linked with the bcfile.
-fn compile(opt: &Opt, package: &str, target: &str) -> CVResult<(PathBuf, Vec<PathBuf>)> {
-    let mut cmd = Command::new("cargo");
-    cmd.arg("build").arg("--manifest-path").arg(&opt.cargo_toml);
+fn compile(
+    opt: &Opt,
+    backend: &dyn Backend,
+    package: &str,
+    target: &str,
+) -> CVResult<(PathBuf, Vec<PathBuf>)> {
+    // Find the target directory
+    // (This may not be inside the crate if using workspaces)
+    info_at!(&opt, Verbosity::Trivial, "  Getting target directory");
+    let target_dir = build_target_directory(opt, backend)?;
 
-    if !opt.features.is_empty() {
-        cmd.arg("--features").arg(opt.features.join(","));
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build")
+        .arg("--manifest-path")
+        .arg(&opt.cargo_toml)
+        .arg("--target-dir")
+        .arg(&target_dir);
+
+    let features = build_features(opt, backend);
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
     }
 
     if opt.tests || !opt.test.is_empty() {
@@ -787,15 +1304,10 @@ fn compile(opt: &Opt, package: &str, target: &str) -> CVResult<(PathBuf, Vec<Pat
     // FIXME: "=="?
     cmd.arg(format!("--target={}", target))
         .args(vec!["-v"; opt.verbose.saturating_sub(1)])
-        .envs(get_build_envs(&opt)?)
+        .envs(get_build_envs(opt, backend)?)
         .output_info(&opt, Verbosity::Normal)?;
     // .env("PATH", ...)
 
-    // Find the target directory
-    // (This may not be inside the crate if using workspaces)
-    info_at!(&opt, Verbosity::Trivial, "  Getting target directory");
-    let target_dir = get_meta_target_directory(&opt)?;
-
     // {target_dir}/{target}/debug/deps/{package}*.bc
     // where the file name has exactly one 1 '.' (because later we add similar
     // files, with multiple dots, and we don't want them here)
@@ -939,11 +1451,20 @@ fn mangle_functions(
 
     info_at!(&opt, Verbosity::Trivial, "      Found {:?}", rs);
 
-    // TODO: this doesn't look right:
-    // missing = set(paths) - paths.keys()
-    let missing = names.len() - rs.len();
-    if missing > 0 {
-        Err(format!("Unable to find {} tests in bytecode file", missing))?
+    // The functions that are missing are left to the caller (e.g., a test can
+    // be missing from the build for one backend only).
+    let missing: Vec<&&str> = names
+        .iter()
+        .filter(|name| !rs.iter().any(|(dname, _)| dname == **name))
+        .collect();
+    if !missing.is_empty() {
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "    Unable to find {:?} in {}",
+            missing,
+            bcfile.to_string_lossy()
+        );
     }
     Ok(rs)
 }
@@ -0,0 +1,147 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Portfolio mode: running several backends on the same test and combining
+// their results.
+////////////////////////////////////////////////////////////////////////////////
+
+use crate::*;
+
+/// Is `result` an answer (as opposed to the backend giving up)?
+pub fn is_conclusive(result: &TestResult) -> bool {
+    match result.status {
+        Status::Unknown | Status::Timeout | Status::OutOfMemory | Status::Unsupported => false,
+        // Incomplete backends (e.g., fuzzing) only fail to find a violation.
+        Status::Verified => backend::find(result.backend).map(|b| b.is_complete()) != Some(false),
+        _ => true,
+    }
+}
+
+/// Combine the results of running several backends on the same test (in the
+/// order of `--backends`).
+///
+/// The result is the first conclusive answer (i.e., the one that took the
+/// least time), unless one backend verified the test and another one found a
+/// violation, in which case the result is `Inconsistent`. If no backend reached
+/// a conclusion, the result of the first backend is used.
+///
+/// With `--stop-early`, the other backends are stopped after the first
+/// conclusive answer (see `verifier_run`), so `results` only has the backends
+/// that finished before then, and disagreements are rarely detected.
+pub fn combine(opt: &Opt, mut results: Vec<TestResult>) -> TestResult {
+    if results.len() == 1 {
+        return results.remove(0);
+    }
+
+    for r in &results {
+        info_at!(
+            &opt,
+            Verbosity::Informative,
+            "  {}: {} says {} ({:.3}s)",
+            r.name,
+            r.backend,
+            r.status,
+            r.time
+        );
+    }
+
+    let verified = results
+        .iter()
+        .any(|r| is_conclusive(r) && r.status == Status::Verified);
+    let failed = results
+        .iter()
+        .any(|r| is_conclusive(r) && r.status != Status::Verified);
+
+    if verified && failed {
+        let mut result = TestResult::new(
+            opt.backend,
+            &results[0].name,
+            &results[0].entry,
+            Status::Inconsistent,
+        );
+        result.backend = backend::PORTFOLIO;
+        result.time = results.iter().map(|r| r.time).fold(0.0, f32::max);
+        for r in &results {
+            result.messages.push(format!("{}: {}", r.backend, r.status));
+            result
+                .messages
+                .extend(r.messages.iter().map(|m| format!("{}: {}", r.backend, m)));
+        }
+        return result;
+    }
+
+    let first = results
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| is_conclusive(r))
+        .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap())
+        .map_or(0, |(i, _)| i);
+    results.remove(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(backend: &dyn Backend, status: Status, time: f32) -> TestResult {
+        let mut result = TestResult::new(backend, "t", "_ZN1t", status);
+        result.time = time;
+        result
+    }
+
+    fn combine_statuses(results: Vec<TestResult>) -> (Status, &'static str) {
+        let opt = Opt::from_iter(&["cargo-verify"]);
+        let result = combine(&opt, results);
+        (result.status, result.backend)
+    }
+
+    #[test]
+    fn first_conclusive_answer() {
+        let results = vec![
+            result(&klee::Klee, Status::Timeout, 1.0),
+            result(&seahorn::Seahorn, Status::Verified, 3.0),
+            result(&smack::Smack, Status::Verified, 2.0),
+        ];
+        assert_eq!(combine_statuses(results), (Status::Verified, "smack"));
+    }
+
+    #[test]
+    fn disagreement() {
+        let results = vec![
+            result(&klee::Klee, Status::Overflow, 1.0),
+            result(&seahorn::Seahorn, Status::Verified, 2.0),
+        ];
+        assert_eq!(
+            combine_statuses(results),
+            (Status::Inconsistent, backend::PORTFOLIO)
+        );
+    }
+
+    #[test]
+    fn all_inconclusive() {
+        let results = vec![
+            result(&klee::Klee, Status::Timeout, 1.0),
+            result(&seahorn::Seahorn, Status::Unknown, 2.0),
+        ];
+        assert_eq!(combine_statuses(results), (Status::Timeout, "klee"));
+    }
+
+    #[test]
+    fn incomplete_verified() {
+        // Fuzzing without finding a failure is not a conclusion, so it does
+        // not disagree with a violation.
+        assert!(!is_conclusive(&result(&fuzz::Fuzz, Status::Verified, 1.0)));
+        assert!(is_conclusive(&result(&fuzz::Fuzz, Status::Panic, 1.0)));
+        let results = vec![
+            result(&fuzz::Fuzz, Status::Verified, 1.0),
+            result(&klee::Klee, Status::Panic, 2.0),
+        ];
+        assert_eq!(combine_statuses(results), (Status::Panic, "klee"));
+    }
+}
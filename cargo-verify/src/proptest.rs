@@ -10,12 +10,67 @@ use log::warn;
 
 use crate::*;
 
-pub fn check_install() -> bool {
-    true
+pub struct Proptest;
+
+impl Backend for Proptest {
+    fn name(&self) -> &'static str {
+        "proptest"
+    }
+
+    fn check_install(&self, _opt: &Opt) -> bool {
+        true
+    }
+
+    fn supports_replay(&self) -> bool {
+        true
+    }
+
+    fn supports_args(&self) -> bool {
+        true
+    }
+
+    fn uses_bitcode(&self) -> bool {
+        false
+    }
+
+    fn check_opt(&self, opt: &Opt) -> CVResult<()> {
+        if opt.output.is_some() {
+            Err("backend proptest does not support --output")?;
+        }
+        if opt.junit.is_some() {
+            Err("backend proptest does not support --junit")?;
+        }
+        if opt.replay > 0 && !opt.args.is_empty() {
+            Err(
+                "The Proptest backend does not support '--replay' and passing arguments together.",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn verify(
+        &self,
+        _opt: &Opt,
+        _name: &str,
+        _entry: &str,
+        _bcfile: &Path,
+    ) -> CVResult<TestResult> {
+        // `uses_bitcode` is false, so `run` is used instead.
+        Err("The proptest backend verifies whole crates, not bitcode files.")?
+    }
+
+    fn run(&self, opt: &Opt, _package: &str) -> CVResult<Status> {
+        let beginning = Instant::now();
+        let status = run(opt)?;
+        if opt.message_format == MessageFormat::Json {
+            report::json_summary(status, None, &[], beginning.elapsed().as_secs_f32())?;
+        }
+        Ok(status)
+    }
 }
 
 /// Run cargo test
-pub fn run(opt: &Opt) -> CVResult<Status> {
+fn run(opt: &Opt) -> CVResult<Status> {
     let mut cmd = Command::new("cargo");
     cmd.arg("test")
         .arg("--manifest-path")
@@ -41,7 +96,11 @@ pub fn run(opt: &Opt) -> CVResult<Status> {
         cmd.arg("--").args(&opt.args);
     }
 
-    match cmd.output_info_ignore_exit(&opt, Verbosity::Major) {
+    match cmd.limited_output_info(opt, Verbosity::Major) {
+        Err(e) if e.is::<LimitExceeded>() => {
+            warn!("Proptest failed '{}'", e);
+            Ok(e.downcast_ref::<LimitExceeded>().unwrap().status())
+        }
         Err(e) => {
             warn!("Proptest failed '{:?}'", e);
             Ok(Status::Error)
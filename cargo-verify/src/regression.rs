@@ -0,0 +1,102 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Converting counterexamples into ordinary regression tests
+// ('--emit-regression-tests DIR').
+//
+// For each failing test, a file "{DIR}/{test}.rs" is generated with one
+// `#[test]` per counterexample. Each test runs the original test with the
+// bytes of the objects in the ktest file as its input: when no verifier
+// feature is selected, verification-annotations reads the non-deterministic
+// values from these bytes in the order that KLEE created them (like a fuzzing
+// corpus, see '--export-corpus'), so the values go through the same
+// strategies as they did in KLEE. The tests run with a plain 'cargo test', as
+// long as the crate uses verification-annotations (or propverify) in that
+// build.
+////////////////////////////////////////////////////////////////////////////////
+
+use ktest::KTest;
+
+use crate::{counterexample::Counterexample, *};
+
+/// Write the regression tests for the counterexamples of the test `name`.
+pub fn emit(opt: &Opt, name: &str, counterexamples: &[Counterexample]) -> CVResult<()> {
+    let dir = match &opt.emit_regression_tests {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    if counterexamples.is_empty() {
+        return Ok(());
+    }
+
+    let fn_name = name.rsplit("::").next().unwrap_or(name);
+    let mut tests = format!(
+        "// Regression tests for '{}', generated by 'cargo verify --emit-regression-tests'\n\
+         // from the counterexamples found by {}.\n\
+         //\n\
+         // To use them, include this file in the module that defines '{}' and run\n\
+         //     cargo test regression_\n\
+         // The module must use verification-annotations (or propverify, not proptest)\n\
+         // when it is not verified: the non-deterministic values are then read from\n\
+         // the bytes of the counterexamples. (Without these bytes, the original test\n\
+         // panics.)\n\
+         \n\
+         #[allow(unused_imports)]\n\
+         use super::*;\n",
+        name, counterexamples[0].backend, fn_name
+    );
+    for c in counterexamples {
+        let stem = c
+            .file
+            .file_stem()
+            .map_or(String::from("counterexample"), |s| {
+                s.to_string_lossy().into_owned()
+            });
+        tests.push_str(&format!(
+            "\n/// Counterexample {} ({}",
+            c.file.to_string_lossy(),
+            c.status
+        ));
+        if let Some(panic) = &c.panic {
+            tests.push_str(&format!(" at {}", panic));
+        }
+        tests.push_str(")\n");
+        for v in &c.values {
+            tests.push_str(&format!("/// - {} = {}\n", v.name, v.value));
+        }
+        let bytes = KTest::read(&c.file)?
+            .program_bytes()
+            .iter()
+            .map(u8::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        tests.push_str(&format!(
+            "#[cfg(not(verify))]\n\
+             #[test]\n\
+             fn regression_{}_{}() {{\n\
+             \x20   verifier::run_with_input(&[{}], {});\n\
+             }}\n",
+            fn_name, stem, bytes, fn_name
+        ));
+    }
+
+    fs::create_dir_all(dir)?;
+    let file = dir
+        .clone()
+        .append(format!("{}.rs", name.replace("::", "_")));
+    info_at!(
+        &opt,
+        Verbosity::Informative,
+        "  Writing regression tests for {} to {}",
+        name,
+        file.to_string_lossy()
+    );
+    fs::write(&file, tests)?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Machine readable reports of the verification results.
+////////////////////////////////////////////////////////////////////////////////
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::*;
+
+/// The records printed by `--message-format=json`, one per line.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Message<'a> {
+    Test(&'a TestResult),
+    Summary {
+        status:   Status,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        passed:   Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        failed:   Option<usize>,
+        /// The number of failed tests with each status
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        statuses: BTreeMap<String, usize>,
+        /// Total time, in seconds
+        time:     f32,
+    },
+}
+
+/// Print the JSON record of a single test.
+pub fn json_test(result: &TestResult) -> CVResult<()> {
+    println!("{}", serde_json::to_string(&Message::Test(result))?);
+    Ok(())
+}
+
+/// Print the JSON summary record. `counts` is the number of passed and failed
+/// tests, if known, and `statuses` the number of failed tests with each status.
+pub fn json_summary(
+    status: Status,
+    counts: Option<(usize, usize)>,
+    statuses: &[(Status, usize)],
+    time: f32,
+) -> CVResult<()> {
+    let message = Message::Summary {
+        status,
+        passed: counts.map(|(passed, _)| passed),
+        failed: counts.map(|(_, failed)| failed),
+        statuses: statuses.iter().map(|(s, n)| (s.to_string(), *n)).collect(),
+        time,
+    };
+    println!("{}", serde_json::to_string(&message)?);
+    Ok(())
+}
+
+/// Write a JUnit XML report with one testcase per test.
+///
+/// `Verified` tests pass, `Timeout`, `OutOfMemory`, `Unknown`, `Inconsistent`,
+/// `Unsupported` and `BuildFailure` tests are reported as errors (the verifiers
+/// could not reach a conclusion) and all other statuses are reported as
+/// failures.
+pub fn write_junit(path: &Path, package: &str, results: &[TestResult]) -> CVResult<()> {
+    let failures = results
+        .iter()
+        .filter(|r| {
+            !matches!(
+                r.status,
+                Status::Verified
+                    | Status::Timeout
+                    | Status::OutOfMemory
+                    | Status::Unknown
+                    | Status::Inconsistent
+                    | Status::Unsupported
+                    | Status::BuildFailure
+            )
+        })
+        .count();
+    let errors = results
+        .iter()
+        .filter(|r| {
+            matches!(
+                r.status,
+                Status::Timeout
+                    | Status::OutOfMemory
+                    | Status::Unknown
+                    | Status::Inconsistent
+                    | Status::Unsupported
+                    | Status::BuildFailure
+            )
+        })
+        .count();
+    let time: f32 = results.iter().map(|r| r.time).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        results.len(),
+        failures,
+        errors,
+        time
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(package),
+        results.len(),
+        failures,
+        errors,
+        time
+    ));
+
+    for result in results {
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+            xml_escape(&result.name),
+            xml_escape(package),
+            result.time
+        ));
+
+        let element = match result.status {
+            Status::Verified => None,
+            Status::Timeout
+            | Status::OutOfMemory
+            | Status::Unknown
+            | Status::Inconsistent
+            | Status::Unsupported
+            | Status::BuildFailure => Some("error"),
+            _ => Some("failure"),
+        };
+        if let Some(element) = element {
+            // Use the first line of the backend output as a short message,
+            // and all of it as the body.
+            let message = result
+                .message
+                .clone()
+                .unwrap_or_else(|| result.status.to_string());
+            xml.push_str(&format!(
+                "\n      <{} type=\"{}\" message=\"{}\">{}</{}>\n    ",
+                element,
+                result.status,
+                xml_escape(&message),
+                xml_escape(&result.messages.join("\n")),
+                element
+            ));
+        }
+
+        xml.push_str("</testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+
+    fs::write(path, xml)?;
+    Ok(())
+}
+
+/// Escape `s` so it can be used in XML attributes and text. Characters that are
+/// not allowed in XML (e.g., from binary output) are replaced with '?'.
+fn xml_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '&' => String::from("&amp;"),
+            '<' => String::from("&lt;"),
+            '>' => String::from("&gt;"),
+            '"' => String::from("&quot;"),
+            '\'' => String::from("&apos;"),
+            '\t' | '\n' | '\r' => c.to_string(),
+            c if c < ' ' => String::from("?"),
+            c => c.to_string(),
+        })
+        .collect()
+}
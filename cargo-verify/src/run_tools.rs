@@ -1,6 +1,19 @@
-use std::{io::Write, iter, str::Lines};
-
-use log::info;
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    iter,
+    os::unix::process::CommandExt,
+    process::{ExitStatus, Output, Stdio},
+    str::Lines,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        mpsc, Arc, Mutex, Once,
+    },
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
 
 use crate::*;
 
@@ -12,12 +25,13 @@ pub trait OutputInfo {
             lvl,
             |v| String::from(from_utf8(v).expect("not UTF-8")),
             false,
+            false,
         )
         .map(|(stdout, stderr, _)| (stdout, stderr))
     }
 
     fn latin1_output_info(&mut self, opt: &Opt, lvl: Verbosity) -> CVResult<(String, String)> {
-        self.output_info_helper(&opt, lvl, utils::from_latin1, false)
+        self.output_info_helper(opt, lvl, utils::from_latin1, false, false)
             .map(|(stdout, stderr, _)| (stdout, stderr))
     }
 
@@ -31,15 +45,35 @@ pub trait OutputInfo {
             lvl,
             |v| String::from(from_utf8(v).expect("not UTF-8")),
             true,
+            false,
+        )
+    }
+
+    /// Like `output_info_ignore_exit`, but the process is killed (and a
+    /// `LimitExceeded` error is returned) if it exceeds the limits set by
+    /// `--timeout` or `--memory-limit`, or (with a `Stopped` error) if it is
+    /// stopped by `stop_when`. This should be used for running the
+    /// verification backends.
+    fn limited_output_info(
+        &mut self,
+        opt: &Opt,
+        lvl: Verbosity,
+    ) -> CVResult<(String, String, bool)> {
+        self.output_info_helper(
+            opt,
+            lvl,
+            |v| String::from(from_utf8(v).expect("not UTF-8")),
+            true,
+            true,
         )
     }
 
-    fn latin1_output_info_ignore_exit(
+    fn latin1_limited_output_info(
         &mut self,
         opt: &Opt,
         lvl: Verbosity,
     ) -> CVResult<(String, String, bool)> {
-        self.output_info_helper(&opt, lvl, utils::from_latin1, true)
+        self.output_info_helper(opt, lvl, utils::from_latin1, true, true)
     }
 
     fn output_info_helper(
@@ -48,6 +82,7 @@ pub trait OutputInfo {
         lvl: Verbosity,
         trans: impl Fn(&[u8]) -> String,
         ignore_exit: bool,
+        limited: bool,
     ) -> CVResult<(String, String, bool)>;
 }
 
@@ -58,6 +93,7 @@ impl OutputInfo for Command {
         lvl: Verbosity,
         trans: impl Fn(&[u8]) -> String,
         ignore_exit: bool,
+        limited: bool,
     ) -> CVResult<(String, String, bool)> {
         info_cmd(&self);
 
@@ -65,7 +101,13 @@ impl OutputInfo for Command {
             eprintln!("Cannot write to script: {:?}", e);
         }
 
-        let output = self.output()?;
+        let stop = STOP.with(|stop| stop.borrow().clone());
+        let output =
+            if limited && (opt.timeout.is_some() || opt.memory_limit.is_some() || stop.is_some()) {
+                output_with_limits(self, opt.timeout, opt.memory_limit, stop)?
+            } else {
+                self.output()?
+            };
 
         let stdout = trans(&output.stdout);
         info_lines(&opt, lvl, "STDOUT: ", stdout.lines());
@@ -91,6 +133,296 @@ impl OutputInfo for Command {
     }
 }
 
+/// The limits enforced by `output_with_limits`.
+#[derive(Debug)]
+pub enum Limit {
+    /// Ran for longer than the number of seconds
+    Timeout(u64),
+    /// Used more than the number of MB
+    OutOfMemory(u64),
+}
+
+/// The error returned when `output_with_limits` kills a process.
+#[derive(Debug)]
+pub struct LimitExceeded {
+    pub limit:  Limit,
+    /// What the process printed (stdout and stderr) before it was killed
+    pub output: String,
+}
+
+impl LimitExceeded {
+    /// The status of a test whose backend was killed.
+    pub fn status(&self) -> Status {
+        match self.limit {
+            Limit::Timeout(_) => Status::Timeout,
+            Limit::OutOfMemory(_) => Status::OutOfMemory,
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Timeout(secs) => write!(f, "killed after {} seconds", secs),
+            Limit::OutOfMemory(mb) => write!(f, "killed after using more than {} MB", mb),
+        }
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.limit.fmt(f)
+    }
+}
+
+impl error::Error for LimitExceeded {}
+
+/// The error returned when `output_with_limits` kills a process because it
+/// was asked to stop (see `stop_when`).
+#[derive(Debug)]
+pub struct Stopped;
+
+impl fmt::Display for Stopped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stopped before it finished")
+    }
+}
+
+impl error::Error for Stopped {}
+
+thread_local! {
+    /// The flag that stops the backends run by this thread (see `stop_when`).
+    static STOP: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Call `f`, killing the backends that it runs (with `limited_output_info`)
+/// as soon as `stop` is set, e.g., by another thread.
+pub fn stop_when<T>(stop: &Arc<AtomicBool>, f: impl FnOnce() -> T) -> T {
+    STOP.with(|s| *s.borrow_mut() = Some(Arc::clone(stop)));
+    let result = f();
+    STOP.with(|s| *s.borrow_mut() = None);
+    result
+}
+
+/// The process groups of the backends that are running (0 for unused slots),
+/// so that the SIGINT handler can forward Ctrl-C to them.
+static RUNNING_GROUPS: [AtomicI32; 256] = [ATOMIC_ZERO; 256];
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_ZERO: AtomicI32 = AtomicI32::new(0);
+
+/// Ctrl-C is only sent to the terminal's foreground process group, which the
+/// backends are not part of (see `output_with_limits`): forward it to them,
+/// then die from it.
+extern "C" fn forward_sigint(_: libc::c_int) {
+    for group in RUNNING_GROUPS.iter() {
+        let pgid = group.load(Ordering::SeqCst);
+        if pgid != 0 {
+            unsafe {
+                libc::killpg(pgid, libc::SIGINT);
+            }
+        }
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+        libc::raise(libc::SIGINT);
+    }
+}
+
+/// Record that the process group `pgid` is running (until the result is
+/// dropped), installing the SIGINT handler the first time.
+fn register_group(pgid: libc::pid_t) -> Option<&'static AtomicI32> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| unsafe {
+        libc::signal(
+            libc::SIGINT,
+            forward_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    });
+    let slot = RUNNING_GROUPS.iter().find(|g| {
+        g.compare_exchange(0, pgid, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    });
+    if slot.is_none() {
+        warn!("Too many backends running: Ctrl-C will not stop them all");
+    }
+    slot
+}
+
+/// The output of a pipe, read in the background (otherwise the process might
+/// block when the pipe is full).
+struct PipeReader {
+    buf:  Arc<Mutex<Vec<u8>>>,
+    done: mpsc::Receiver<()>,
+}
+
+impl PipeReader {
+    fn new(mut pipe: impl Read + Send + 'static) -> PipeReader {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        let (sender, done) = mpsc::channel();
+        let thread_buf = Arc::clone(&buf);
+        thread::spawn(move || {
+            let mut chunk = [0; 4096];
+            loop {
+                match pipe.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => thread_buf.lock().unwrap().extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                    Err(_) => break,
+                }
+            }
+            sender.send(()).unwrap_or_default();
+        });
+        PipeReader { buf, done }
+    }
+
+    /// Return what has been read, after waiting for the end of the output.
+    /// Processes that inherited the pipe (e.g., children of a killed backend
+    /// that are still running) can keep it open, so we give up after a
+    /// second and leave the thread reading in the background.
+    fn finish(self) -> Vec<u8> {
+        self.done
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap_or_default();
+        let mut buf = self.buf.lock().unwrap();
+        std::mem::take(&mut *buf)
+    }
+}
+
+/// Run `cmd` and collect its output, like `Command::output`, but kill the
+/// process (and all its children) if it runs for more than `timeout` seconds,
+/// if it uses more than `memory_limit` MB, or when `stop` is set.
+fn output_with_limits(
+    cmd: &mut Command,
+    timeout: Option<u64>,
+    memory_limit: Option<u64>,
+    stop: Option<Arc<AtomicBool>>,
+) -> CVResult<Output> {
+    // Put the process in a new process group, so that we can kill it
+    // together with any process it spawns (e.g., `sea` is a Python script
+    // that runs other tools).
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pgid = child.id() as libc::pid_t;
+    let group = register_group(pgid);
+
+    let stdout = PipeReader::new(child.stdout.take().ok_or("missing stdout")?);
+    let stderr = PipeReader::new(child.stderr.take().ok_or("missing stderr")?);
+
+    let beginning = Instant::now();
+    let mut exceeded = None;
+    let mut stopped = false;
+    let result: CVResult<ExitStatus> = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => (),
+            Err(e) => break Err(e.into()),
+        }
+
+        exceeded = match (timeout, memory_limit) {
+            (Some(secs), _) if beginning.elapsed() > Duration::from_secs(secs) => {
+                Some(Limit::Timeout(secs))
+            }
+            (_, Some(mb)) if process_group_memory(pgid) > mb * 1024 * 1024 => {
+                Some(Limit::OutOfMemory(mb))
+            }
+            _ => None,
+        };
+
+        stopped = matches!(&stop, Some(s) if s.load(Ordering::SeqCst));
+
+        if stopped || exceeded.is_some() {
+            match &exceeded {
+                Some(limit) => info!("'{}' {}", cmd.get_program().to_string_lossy(), limit),
+                None => info!("'{}' {}", cmd.get_program().to_string_lossy(), Stopped),
+            }
+            unsafe {
+                libc::killpg(pgid, libc::SIGKILL);
+            }
+            break child.wait().map_err(Into::into);
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    };
+    if let Some(group) = group {
+        group.store(0, Ordering::SeqCst);
+    }
+
+    let stdout = stdout.finish();
+    let stderr = stderr.finish();
+    if stopped {
+        Err(Stopped)?
+    }
+    if let Some(limit) = exceeded {
+        let output = String::from_utf8_lossy(&stdout) + String::from_utf8_lossy(&stderr);
+        Err(LimitExceeded {
+            limit,
+            output: output.into_owned(),
+        })?
+    }
+    Ok(Output {
+        status: result?,
+        stdout,
+        stderr,
+    })
+}
+
+/// Total resident memory (in bytes) of all the processes in process group
+/// `pgid`.
+#[cfg(target_os = "linux")]
+fn process_group_memory(pgid: libc::pid_t) -> u64 {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+
+    fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    // /proc/[pid]/stat is "pid (comm) state ppid pgrp ...", and
+                    // comm might contain spaces and parentheses.
+                    fs::read_to_string(entry.path().append("stat"))
+                        .ok()
+                        .and_then(|stat| {
+                            stat.rsplit_once(')')
+                                .and_then(|(_, fields)| fields.split_whitespace().nth(2))
+                                .and_then(|pgrp| pgrp.parse::<libc::pid_t>().ok())
+                        })
+                        == Some(pgid)
+                })
+                .filter_map(|entry| {
+                    // /proc/[pid]/statm is "size resident ..." in pages.
+                    fs::read_to_string(entry.path().append("statm"))
+                        .ok()
+                        .and_then(|statm| {
+                            statm
+                                .split_whitespace()
+                                .nth(1)
+                                .and_then(|rss| rss.parse::<u64>().ok())
+                        })
+                })
+                .sum::<u64>()
+                * page_size
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_group_memory(_pgid: libc::pid_t) -> u64 {
+    // `--memory-limit` is rejected on other platforms.
+    0
+}
+
 /// Log `cmd` nicely.
 fn info_cmd(cmd: &Command) {
     info!(
@@ -285,17 +617,22 @@ pub fn count_symbols(opt: &Opt, bcfile: &Path, fs: &[&str]) -> CVResult<usize> {
     Ok(count)
 }
 
-/// Generate a list of tests in the crate by parsing the output of
-/// `cargo test -- --list`
-pub fn list_tests(opt: &Opt, target: &str) -> CVResult<Vec<String>> {
+/// Generate a list of tests in the crate (when built for `backend`) by parsing
+/// the output of `cargo test -- --list`
+pub fn list_tests(opt: &Opt, backend: &dyn Backend, target: &str) -> CVResult<Vec<String>> {
     let mut cmd = Command::new("cargo");
-    cmd.arg("test").arg("--manifest-path").arg(&opt.cargo_toml);
+    cmd.arg("test")
+        .arg("--manifest-path")
+        .arg(&opt.cargo_toml)
+        .arg("--target-dir")
+        .arg(build_target_directory(opt, backend)?);
 
     if opt.no_default_features {
         cmd.arg("--no-default-features");
     }
-    if !opt.features.is_empty() {
-        cmd.arg("--features").arg(opt.features.join(","));
+    let features = build_features(opt, backend);
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
     }
 
     // Surprisingly, the effect of the following line is to prevent
@@ -307,7 +644,7 @@ pub fn list_tests(opt: &Opt, target: &str) -> CVResult<Vec<String>> {
 
     cmd.arg(format!("--target={}", target))
         .args(vec!["-v"; opt.verbose])
-        .envs(get_build_envs(&opt)?)
+        .envs(get_build_envs(opt, backend)?)
         .args(&["--", "--list"]);
     // .arg("--exclude-should-panic")
     // .env("PATH", ...)
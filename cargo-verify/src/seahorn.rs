@@ -6,25 +6,78 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::{ffi::OsString, fs, path::Path, process::Command};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    path::Path,
+    process::Command,
+};
 
 use log::{info, warn};
 
-use crate::{utils::Append, *};
+use crate::{
+    counterexample::{self, Counterexample},
+    utils::Append,
+    *,
+};
 
-/// Check if Seahorn is avilable.
-pub fn check_install() -> bool {
-    // TODO: maybe it's better to check `seahorn --version`?
-    let output = Command::new("which").arg("sea").output().ok();
+pub struct Seahorn;
 
-    match output {
-        Some(output) => output.status.success(),
-        None => false,
+impl Backend for Seahorn {
+    fn name(&self) -> &'static str {
+        "seahorn"
+    }
+
+    /// Check if Seahorn is avilable.
+    fn check_install(&self, _opt: &Opt) -> bool {
+        // TODO: maybe it's better to check `seahorn --version`?
+        let output = Command::new("which").arg("sea").output().ok();
+
+        match output {
+            Some(output) => output.status.success(),
+            None => false,
+        }
+    }
+
+    fn version(&self, opt: &Opt) -> CVResult<String> {
+        backends_common::tool_version(opt, "sea")
+    }
+
+    fn supports_replay(&self) -> bool {
+        true
+    }
+
+    fn supports_args(&self) -> bool {
+        true
+    }
+
+    fn supports_sym_stdin(&self) -> bool {
+        true
+    }
+
+    fn reports_unclassified_failures(&self) -> bool {
+        true
+    }
+
+    fn needs_mangled_main(&self) -> bool {
+        true
+    }
+
+    fn features(&self) -> &'static [&'static str] {
+        &["verifier-seahorn"]
+    }
+
+    fn patch_llvm_options(&self) -> &'static [&'static str] {
+        &["--seahorn"]
+    }
+
+    fn verify(&self, opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
+        verify(opt, name, entry, bcfile)
     }
 }
 
-/// Run Seahorn
-pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Status> {
+/// Run Seahorn and replay
+fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
     let out_dir = opt.cargo_toml.with_file_name("seaout").append(name);
 
     // Ignoring result. We don't care if it fails because the path doesn't
@@ -43,7 +96,63 @@ pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Sta
     info!("      entry: {}", entry);
     info!("      results: {}", out_dir.to_string_lossy());
 
-    run(&opt, &name, &entry, &bcfile, &out_dir)
+    // Seahorn can't pass arguments to the program, so they (and the size of
+    // symbolic standard input) are baked into the bitcode file.
+    let (run_bcfile, run_entry) = backends_common::patch_inputs(opt, bcfile, entry, &out_dir)?;
+
+    let (status, messages) = run(opt, name, &run_entry, &run_bcfile, &out_dir)?;
+
+    // Seahorn is asked to generate a counterexample harness (see `run`), which
+    // defines the `__VERIFIER_nondet_*` functions.
+    let harness = out_dir.clone().append("harness.ll");
+    let counterexamples = if harness.exists() {
+        vec![harness]
+    } else {
+        vec![]
+    };
+
+    let mut replay_outputs = Vec::new();
+    if opt.replay > 0 && status != Status::Verified {
+        if counterexamples.is_empty() {
+            warn!("Seahorn did not generate a counterexample for {}", name);
+        }
+        for harness in &counterexamples {
+            output!(&opt, "    Test input {}", harness.to_str().unwrap_or("???"));
+            match replay_seahorn(opt, name, harness) {
+                Ok(output) => replay_outputs.push((harness, output)),
+                Err(err) => warn!("Failed to replay: {}", err),
+            }
+        }
+    }
+    let replay_output: String = replay_outputs.iter().map(|(_, o)| o.as_str()).collect();
+
+    // Seahorn does not run the program, so the expected status (if any) is
+    // only printed when a counterexample is replayed.
+    let status = backends_common::check_expected_status(
+        &Seahorn,
+        name,
+        status,
+        backends_common::expected_status(&replay_output, name),
+    );
+
+    let mut result = TestResult::new(&Seahorn, name, entry, status);
+    result.output_dir = Some(out_dir);
+    result.messages = messages;
+
+    let saved = counterexamples
+        .iter()
+        .map(|harness| {
+            let mut counterexample = Counterexample::new(&result, harness);
+            if let Some((_, output)) = replay_outputs.iter().find(|(h, _)| h == &harness) {
+                counterexample.add_replay_output(output);
+            }
+            counterexample
+        })
+        .collect::<Vec<_>>();
+    counterexample::save(opt, &result, &saved)?;
+
+    result.counterexamples = counterexamples;
+    Ok(result)
 }
 
 /// Return an int indicating importance of a line from KLEE's output
@@ -78,7 +187,14 @@ fn importance(line: &str, expect: &Option<&str>, name: &str) -> i8 {
 }
 
 /// Run Seahorn and analyse its output.
-fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVResult<Status> {
+/// Return the status and the lines of output that describe errors.
+fn run(
+    opt: &Opt,
+    name: &str,
+    entry: &str,
+    bcfile: &Path,
+    out_dir: &Path,
+) -> CVResult<(Status, Vec<String>)> {
     let verify_common_dir = match &opt.seahorn_verify_c_common_dir {
         Some(verify_common_dir) => verify_common_dir,
         None => Err("The '--seahorn-verify-c-common-dir' option is missing")?,
@@ -98,14 +214,15 @@ fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVR
             .arg(format!("{}/seahorn/sea_base.yaml", verify_common_dir))
             .arg("bpf")
             .arg(OsString::from("--temp-dir=").append(out_dir))
-            .arg(String::from("--entry=") + entry)
-            .args(user_flags)
-            .arg(&bcfile);
+            .arg(String::from("--entry=") + entry);
+        // The harness is only generated if verification fails.
+        cmd.arg(OsString::from("--cex=").append(out_dir.to_path_buf().append("harness.ll")));
+        cmd.args(user_flags).arg(bcfile);
     } else {
         cmd.args(user_flags);
     }
 
-    let (stdout, stderr, _) = cmd.output_info_ignore_exit(&opt, Verbosity::Major)?;
+    let (stdout, stderr, _) = cmd.limited_output_info(opt, Verbosity::Major)?;
 
     // We scan stderr for:
     // 1. Indications of the expected output (eg from #[should_panic])
@@ -163,9 +280,38 @@ fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVR
 
     for l in stderr.lines() {
         if importance(&l, &expect, &name) < opt.verbose as i8 {
-            println!("{}", l);
+            output!(&opt, "{}", l);
         }
     }
 
-    Ok(status)
+    let messages = stderr
+        .lines()
+        .filter(|l| matches!(importance(l, &expect, name), -1 | 1 | 2))
+        .map(String::from)
+        .collect();
+
+    Ok((status, messages))
+}
+
+/// Replay a Seahorn counterexample harness: compile it, and link it with the
+/// natively built test, where it replaces the dummy definitions of the
+/// `__VERIFIER_nondet_*` functions.
+fn replay_seahorn(opt: &Opt, name: &str, harness: &Path) -> CVResult<String> {
+    let harness_obj = harness.with_extension("o");
+    Command::new(format!("clang-{}", opt.llvm_version))
+        .arg("-c")
+        .arg(harness)
+        .arg("-o")
+        .arg(&harness_obj)
+        .output_info(opt, Verbosity::Major)?;
+
+    backends_common::replay(
+        opt,
+        name,
+        &backends_common::ReplayInput {
+            rustflags: vec![OsString::from("-Clink-arg=").append(&harness_obj)],
+            envs: vec![("SEAHORN_REPLAY", OsStr::new("1"))],
+            ..Default::default()
+        },
+    )
 }
@@ -8,22 +8,83 @@
 
 use std::{ffi::OsString, fs, path::Path, process::Command};
 
+use lazy_static::lazy_static;
 use log::{info, warn};
+use regex::Regex;
 
-use crate::{utils::Append, *};
+use crate::{
+    counterexample::{self, Counterexample},
+    utils::Append,
+    *,
+};
 
-/// Check if SMACK is available.
-pub fn check_install() -> bool {
-    let output = Command::new("which").arg("smack").output().ok();
+pub struct Smack;
 
-    match output {
-        Some(output) => output.status.success(),
-        None => false,
+impl Backend for Smack {
+    fn name(&self) -> &'static str {
+        "smack"
+    }
+
+    /// Check if SMACK is available.
+    fn check_install(&self, _opt: &Opt) -> bool {
+        let output = Command::new("which").arg("smack").output().ok();
+
+        match output {
+            Some(output) => output.status.success(),
+            None => false,
+        }
+    }
+
+    fn version(&self, opt: &Opt) -> CVResult<String> {
+        backends_common::tool_version(opt, "smack")
+    }
+
+    fn supports_replay(&self) -> bool {
+        true
+    }
+
+    fn supports_args(&self) -> bool {
+        true
+    }
+
+    fn supports_sym_stdin(&self) -> bool {
+        true
+    }
+
+    fn reports_unclassified_failures(&self) -> bool {
+        true
+    }
+
+    fn needs_mangled_main(&self) -> bool {
+        // '--smack' deletes the C main function generated by rustc.
+        true
+    }
+
+    fn features(&self) -> &'static [&'static str] {
+        &["verifier-smack"]
+    }
+
+    fn rustflags(&self) -> &'static [&'static str] {
+        // Flags for compiling for smack
+        &[
+            "-Copt-level=0",
+            "-Cno-prepopulate-passes",
+            "-g",
+            "-Cpasses=name-anon-globals",
+        ]
+    }
+
+    fn patch_llvm_options(&self) -> &'static [&'static str] {
+        &["--smack"]
+    }
+
+    fn verify(&self, opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
+        verify(opt, name, entry, bcfile)
     }
 }
 
-/// Run SMACK
-pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Status> {
+/// Run SMACK and replay
+fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<TestResult> {
     let out_dir = opt.cargo_toml.with_file_name("smackout").append(name);
 
     // Ignoring result. We don't care if it fails because the path doesn't
@@ -42,11 +103,86 @@ pub fn verify(opt: &Opt, name: &str, entry: &str, bcfile: &Path) -> CVResult<Sta
     info!("      entry: {}", entry);
     info!("      results: {}", out_dir.to_string_lossy());
 
-    run(&opt, &name, &entry, &bcfile, &out_dir)
+    // SMACK can't pass arguments to the program, so they (and the size of
+    // symbolic standard input) are baked into the bitcode file.
+    let (run_bcfile, run_entry) = backends_common::patch_inputs(opt, bcfile, entry, &out_dir)?;
+
+    let (status, messages, values) = run(opt, name, &run_entry, &run_bcfile, &out_dir)?;
+
+    // Save the values from the counterexample trace, in the format expected
+    // by the replay runtime in verification-annotations (lib/smack-rust.c).
+    let mut counterexamples = Vec::new();
+    if status != Status::Verified && !values.is_empty() {
+        let replay_file = out_dir.clone().append("replay.txt");
+        fs::write(
+            &replay_file,
+            values
+                .iter()
+                .map(|v| format!("{}\n", *v as u128))
+                .collect::<String>(),
+        )?;
+        counterexamples.push(replay_file);
+    }
+
+    let mut replay_outputs = Vec::new();
+    if opt.replay > 0 && status != Status::Verified {
+        if counterexamples.is_empty() {
+            warn!("SMACK did not report a counterexample for {}", name);
+        }
+        for replay_file in &counterexamples {
+            output!(
+                &opt,
+                "    Test input {}",
+                replay_file.to_str().unwrap_or("???")
+            );
+            match replay_smack(opt, name, replay_file) {
+                Ok(output) => replay_outputs.push((replay_file, output)),
+                Err(err) => warn!("Failed to replay: {}", err),
+            }
+        }
+    }
+    let replay_output: String = replay_outputs.iter().map(|(_, o)| o.as_str()).collect();
+
+    // SMACK does not run the program, so the expected status (if any) is
+    // only printed when a counterexample is replayed.
+    let status = backends_common::check_expected_status(
+        &Smack,
+        name,
+        status,
+        backends_common::expected_status(&replay_output, name),
+    );
+
+    let mut result = TestResult::new(&Smack, name, entry, status);
+    result.output_dir = Some(out_dir);
+    result.messages = messages;
+
+    let saved = counterexamples
+        .iter()
+        .map(|replay_file| {
+            let mut counterexample = Counterexample::new(&result, replay_file);
+            if let Some((_, output)) = replay_outputs.iter().find(|(f, _)| f == &replay_file) {
+                counterexample.add_replay_output(output);
+            }
+            counterexample
+        })
+        .collect::<Vec<_>>();
+    counterexample::save(opt, &result, &saved)?;
+
+    result.counterexamples = counterexamples;
+    Ok(result)
 }
 
 /// Run Smack and analyse its output.
-fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVResult<Status> {
+/// Return the status, the lines of output that describe errors, and the values
+/// returned by the `__VERIFIER_nondet_*` functions in the counterexample trace
+/// (if there is one).
+fn run(
+    opt: &Opt,
+    name: &str,
+    entry: &str,
+    bcfile: &Path,
+    out_dir: &Path,
+) -> CVResult<(Status, Vec<String>, Vec<i128>)> {
     let mut cmd = Command::new("smack");
 
     let user_flags: Vec<_> = opt
@@ -59,7 +195,7 @@ fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVR
         .args(user_flags)
         .arg(String::from("--entry-points=") + entry)
         .arg(bcfile);
-    let (stdout, stderr, _) = cmd.output_info_ignore_exit(&opt, Verbosity::Major)?;
+    let (stdout, stderr, _) = cmd.limited_output_info(opt, Verbosity::Major)?;
 
     // Scan for result mesage
     let status = stderr
@@ -69,7 +205,7 @@ fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVR
             if l.starts_with("SMACK found no errors") {
                 Some(Status::Verified)
             } else if l.starts_with("SMACK found an error") {
-                Some(Status::Error)
+                Some(smack_error_status(l))
             } else {
                 None
             }
@@ -79,5 +215,82 @@ fn run(opt: &Opt, name: &str, entry: &str, bcfile: &Path, out_dir: &Path) -> CVR
             Status::Unknown
         });
 
-    Ok(status)
+    let messages = stderr
+        .lines()
+        .chain(stdout.lines())
+        .filter(|l| {
+            l.starts_with("SMACK found an error")
+                || l.contains("ERROR")
+                || l.contains("This assertion can fail")
+        })
+        .map(String::from)
+        .collect();
+
+    let values = nondet_values(stderr.lines().chain(stdout.lines()));
+
+    Ok((status, messages, values))
+}
+
+/// The values returned by the `__VERIFIER_nondet_*` functions in the execution
+/// trace, which has lines like this one
+/// "src/main.rs(7,13): Trace: Thread=1  (RETURN from __VERIFIER_nondet_u32  == 42)"
+fn nondet_values<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<i128> {
+    lazy_static! {
+        static ref NONDET: Regex =
+            Regex::new(r"RETURN from __VERIFIER_nondet_\w+\s+==\s+(-?\d+)(bv\d+)?\s*\)").unwrap();
+    }
+    lines
+        .filter_map(|l| NONDET.captures(l))
+        .filter_map(|caps| {
+            // Unsigned 128-bit values can be too large for an i128 (and they
+            // are saved as bit patterns anyway).
+            caps[1]
+                .parse::<i128>()
+                .or_else(|_| caps[1].parse::<u128>().map(|v| v as i128))
+                .ok()
+        })
+        .collect()
+}
+
+/// Classify a "SMACK found an error: ..." message.
+fn smack_error_status(l: &str) -> Status {
+    if l.contains("memory leak") {
+        Status::MemoryLeak
+    } else if l.contains("invalid pointer") || l.contains("invalid memory") {
+        Status::InvalidPointer
+    } else if l.contains("overflow") {
+        Status::Overflow
+    } else if l.contains("division by zero") {
+        Status::DivisionByZero
+    } else {
+        Status::Error
+    }
+}
+
+/// Replay a SMACK counterexample, using the values saved in `replay_file`.
+fn replay_smack(opt: &Opt, name: &str, replay_file: &Path) -> CVResult<String> {
+    backends_common::replay(
+        opt,
+        name,
+        &backends_common::ReplayInput {
+            envs: vec![("SMACK_REPLAY_FILE", replay_file.as_os_str())],
+            ..Default::default()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_values() {
+        let trace = "\
+src/main.rs(7,13): Trace: Thread=1  (RETURN from __VERIFIER_nondet_u32  == 42)
+src/main.rs(8,13): Trace: Thread=1  (RETURN from __VERIFIER_nondet_i8  == -3bv8 )
+src/main.rs(9,13): Trace: Thread=1  (CALL __VERIFIER_nondet_u64)
+src/main.rs(9,13): Trace: Thread=1  (RETURN from __VERIFIER_nondet_u128  == 340282366920938463463374607431768211455)
+";
+        assert_eq!(nondet_values(trace.lines()), vec![42, -3, -1]);
+    }
 }
@@ -17,13 +17,26 @@ use std::{
     path::{Path, PathBuf},
 };
 
-/// `info_at!(&opt, level, ...)` will print the formatted message `...` if
-/// verbosity level is `level` or higher.
+/// `output!(&opt, ...)` will print the formatted message `...` (e.g., the
+/// output of a replayed test) to stdout or, with '--message-format=json', to
+/// stderr so that stdout only has the JSON messages.
+macro_rules! output {
+    ($opt:expr, $($arg:tt)+) => ({
+        if $opt.message_format == crate::MessageFormat::Json {
+            eprintln!($($arg)+);
+        } else {
+            println!($($arg)+);
+        }
+    });
+}
+
+/// `info_at!(&opt, level, ...)` will print the formatted message `...` (see
+/// `output!`) if verbosity level is `level` or higher.
 macro_rules! info_at {
     ($opt:expr, $lvl:expr, $($arg:tt)+) => ({
         let lvl = $lvl;
         if lvl <= $opt.verbosity {
-            println!($($arg)+);
+            output!($opt, $($arg)+);
         }
     });
 }
@@ -0,0 +1,267 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Reading KLEE's error reports ("testNNNNNN.KIND.err").
+//
+// A report looks like
+//   Error: MESSAGE
+//   File: FILE
+//   Line: LINE
+//   assembly.ll line: N
+//   State: N
+//   Stack:
+//       #{DEPTH}{ASSEMBLY_LINE} in FUNCTION (ARGS) at FILE:LINE
+//       ...
+//   Info:
+//   ...
+// where the stack frames are indented by a tab, ASSEMBLY_LINE is 8 digits
+// wide, and the location of each stack frame is only present if the function
+// has debug information.
+////////////////////////////////////////////////////////////////////////////////
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::invalid_data;
+
+/// The kind of error (from the name of the report).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A call to abort
+    Abort,
+    /// An assertion failure (from klee_assert)
+    Assert,
+    /// A division by zero
+    Div,
+    /// An error in an external call
+    External,
+    /// A memory error (e.g., an out of bounds access)
+    Ptr,
+    /// Freeing invalid memory
+    Free,
+    /// An unsupported instruction or intrinsic
+    Exec,
+    /// An arithmetic overflow
+    Overflow,
+    /// A write to read-only memory
+    ReadOnly,
+    /// A call to klee_report_error
+    ReportError,
+    /// Misuse of the KLEE API
+    User,
+    /// An unsupported feature of the model (e.g., symbolic size allocations)
+    Model,
+    Other(String),
+}
+
+impl ErrorKind {
+    fn from_name(name: &str) -> ErrorKind {
+        match name {
+            "abort" => ErrorKind::Abort,
+            "assert" => ErrorKind::Assert,
+            "div" => ErrorKind::Div,
+            "external" => ErrorKind::External,
+            "ptr" => ErrorKind::Ptr,
+            "free" => ErrorKind::Free,
+            "exec" => ErrorKind::Exec,
+            "overflow" => ErrorKind::Overflow,
+            "readonly" => ErrorKind::ReadOnly,
+            "reporterror" => ErrorKind::ReportError,
+            "user" => ErrorKind::User,
+            "model" => ErrorKind::Model,
+            _ => ErrorKind::Other(String::from(name)),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Abort => "abort",
+            ErrorKind::Assert => "assert",
+            ErrorKind::Div => "div",
+            ErrorKind::External => "external",
+            ErrorKind::Ptr => "ptr",
+            ErrorKind::Free => "free",
+            ErrorKind::Exec => "exec",
+            ErrorKind::Overflow => "overflow",
+            ErrorKind::ReadOnly => "readonly",
+            ErrorKind::ReportError => "reporterror",
+            ErrorKind::User => "user",
+            ErrorKind::Model => "model",
+            ErrorKind::Other(name) => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A frame of the stack when the error happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The (mangled) name of the function
+    pub function:      String,
+    /// The line in KLEE's "assembly.ll" file
+    pub assembly_line: Option<u32>,
+    /// The source file (if known)
+    pub file:          Option<PathBuf>,
+    /// The line in `file` (if known)
+    pub line:          Option<u32>,
+}
+
+impl Frame {
+    /// Parse a line of the stack ("#000000123 in f (...) at file:line").
+    fn parse(l: &str) -> Option<Frame> {
+        let l = l.trim().strip_prefix('#')?;
+        let (position, rest) = l.split_once(" in ")?;
+        // The depth is followed by the 8 digits of the assembly line.
+        let position = position.trim();
+        let assembly_line = position
+            .get(position.len().saturating_sub(8)..)
+            .and_then(|n| n.parse().ok());
+        let function = rest.split(" (").next()?.trim();
+
+        let (file, line) = match rest.rsplit_once(") at ") {
+            Some((_, location)) => match location.rsplit_once(':') {
+                Some((file, line)) => (Some(PathBuf::from(file)), line.trim().parse().ok()),
+                None => (Some(PathBuf::from(location)), None),
+            },
+            None => (None, None),
+        };
+        Some(Frame {
+            function: String::from(function),
+            assembly_line,
+            file,
+            line,
+        })
+    }
+
+    /// The source location of the frame ("FILE:LINE"), if known.
+    pub fn location(&self) -> Option<String> {
+        Some(format!(
+            "{}:{}",
+            self.file.as_ref()?.to_string_lossy(),
+            self.line?
+        ))
+    }
+}
+
+/// An error report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KleeError {
+    pub kind:          ErrorKind,
+    /// The message ("Error: ..." line)
+    pub message:       String,
+    /// The source file where the error happened (if known)
+    pub file:          Option<PathBuf>,
+    /// The line in `file` (if known)
+    pub line:          Option<u32>,
+    /// The line in KLEE's "assembly.ll" file
+    pub assembly_line: Option<u32>,
+    /// The stack, innermost frame first
+    pub stack:         Vec<Frame>,
+}
+
+impl KleeError {
+    /// Read an error report ("testNNNNNN.KIND.err").
+    pub fn read(path: &Path) -> io::Result<KleeError> {
+        let kind = path
+            .file_stem()
+            .and_then(|stem| Path::new(stem).extension())
+            .and_then(|kind| kind.to_str())
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "{} is not a KLEE error report",
+                    path.to_string_lossy()
+                ))
+            })?;
+        let mut error = KleeError::parse(&fs::read_to_string(path)?)
+            .ok_or_else(|| invalid_data(format!("{}: malformed", path.to_string_lossy())))?;
+        error.kind = ErrorKind::from_name(kind);
+        Ok(error)
+    }
+
+    /// Parse the contents of an error report (the kind is not part of the
+    /// contents).
+    fn parse(contents: &str) -> Option<KleeError> {
+        let mut lines = contents.lines();
+        let message = lines.next()?.strip_prefix("Error: ")?;
+
+        let mut error = KleeError {
+            kind:          ErrorKind::Other(String::new()),
+            message:       String::from(message),
+            file:          None,
+            line:          None,
+            assembly_line: None,
+            stack:         Vec::new(),
+        };
+        let mut in_stack = false;
+        for l in lines {
+            if in_stack && l.starts_with(char::is_whitespace) {
+                error.stack.extend(Frame::parse(l));
+                continue;
+            }
+            in_stack = false;
+            if let Some(file) = l.strip_prefix("File: ") {
+                error.file = Some(PathBuf::from(file));
+            } else if let Some(line) = l.strip_prefix("Line: ") {
+                error.line = line.trim().parse().ok();
+            } else if let Some(line) = l.strip_prefix("assembly.ll line: ") {
+                error.assembly_line = line.trim().parse().ok();
+            } else if l.starts_with("Stack:") {
+                in_stack = true;
+            }
+        }
+        Some(error)
+    }
+
+    /// The source location of the error ("FILE:LINE"), if known.
+    pub fn location(&self) -> Option<String> {
+        Some(format!(
+            "{}:{}",
+            self.file.as_ref()?.to_string_lossy(),
+            self.line?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let error = KleeError::parse(
+            "Error: memory error: out of bound pointer\n\
+             File: /src/main.rs\n\
+             Line: 12\n\
+             assembly.ll line: 345\n\
+             State: 1\n\
+             Stack: \n\
+             \t#000000345 in _ZN4test4main17h0123456789abcdefE () at /src/main.rs:12\n\
+             \t#100000021 in main (=1, =94) \n\
+             Info: \n\
+             \taddress: 0\n",
+        )
+        .unwrap();
+        assert_eq!(error.message, "memory error: out of bound pointer");
+        assert_eq!(error.location().as_deref(), Some("/src/main.rs:12"));
+        assert_eq!(error.assembly_line, Some(345));
+        assert_eq!(error.stack.len(), 2);
+        assert_eq!(error.stack[0].function, "_ZN4test4main17h0123456789abcdefE");
+        assert_eq!(
+            error.stack[0].location().as_deref(),
+            Some("/src/main.rs:12")
+        );
+        assert_eq!(error.stack[1].function, "main");
+        assert_eq!(error.stack[1].assembly_line, Some(21));
+        assert_eq!(error.stack[1].location(), None);
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Reading and writing KLEE's "ktest" files (test cases).
+//
+// The format (all integers are 32-bit big-endian):
+//   "KTEST" version
+//   numArgs { len bytes }*                  (the KLEE command line)
+//   symArgvs symArgvLen                     (only if version >= 2)
+//   numObjects { nameLen name len bytes }*  (the symbolic objects)
+////////////////////////////////////////////////////////////////////////////////
+
+use std::{convert::TryInto, ffi::OsString, fs, io, os::unix::ffi::OsStringExt, path::Path};
+
+use crate::invalid_data;
+
+const MAGIC: &[u8] = b"KTEST";
+const VERSION: u32 = 3;
+
+/// A symbolic object and its concrete value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KTestObject {
+    pub name:  String,
+    pub bytes: Vec<u8>,
+}
+
+/// The contents of a ktest file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KTest {
+    /// The command line KLEE was invoked with
+    pub args:         Vec<String>,
+    pub sym_argvs:    u32,
+    pub sym_argv_len: u32,
+    pub objects:      Vec<KTestObject>,
+}
+
+impl KTest {
+    /// Read a ktest file.
+    pub fn read(path: &Path) -> io::Result<KTest> {
+        let data = fs::read(path)?;
+        let mut reader = Reader {
+            data: &data,
+            pos:  0,
+        };
+
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid_data(format!(
+                "{} is not a ktest file",
+                path.to_string_lossy()
+            )));
+        }
+        let version = reader.u32()?;
+        if version > VERSION {
+            return Err(invalid_data(format!(
+                "{}: unsupported ktest version {}",
+                path.to_string_lossy(),
+                version
+            )));
+        }
+
+        let num_args = reader.u32()?;
+        let args = (0..num_args)
+            .map(|_| reader.string())
+            .collect::<io::Result<_>>()?;
+
+        let (sym_argvs, sym_argv_len) = if version >= 2 {
+            (reader.u32()?, reader.u32()?)
+        } else {
+            (0, 0)
+        };
+
+        let num_objects = reader.u32()?;
+        let objects = (0..num_objects)
+            .map(|_| {
+                Ok(KTestObject {
+                    name:  reader.string()?,
+                    bytes: reader.block()?.to_vec(),
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(KTest {
+            args,
+            sym_argvs,
+            sym_argv_len,
+            objects,
+        })
+    }
+
+    /// Write a ktest file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fn push_block(data: &mut Vec<u8>, bytes: &[u8]) {
+            data.extend(&(bytes.len() as u32).to_be_bytes());
+            data.extend(bytes);
+        }
+
+        let mut data = Vec::from(MAGIC);
+        data.extend(&VERSION.to_be_bytes());
+
+        data.extend(&(self.args.len() as u32).to_be_bytes());
+        for arg in &self.args {
+            push_block(&mut data, arg.as_bytes());
+        }
+
+        data.extend(&self.sym_argvs.to_be_bytes());
+        data.extend(&self.sym_argv_len.to_be_bytes());
+
+        data.extend(&(self.objects.len() as u32).to_be_bytes());
+        for object in &self.objects {
+            push_block(&mut data, object.name.as_bytes());
+            push_block(&mut data, &object.bytes);
+        }
+
+        fs::write(path, data)
+    }
+
+    /// The symbolic command line arguments (from KLEE's POSIX runtime
+    /// '-sym-args' option).
+    pub fn sym_args(&self) -> Vec<OsString> {
+        self.posix_objects()
+            .iter()
+            .filter(|o| is_sym_arg(&o.name))
+            .map(|o| {
+                // The arguments are NUL terminated.
+                let len = o
+                    .bytes
+                    .iter()
+                    .position(|b| *b == 0)
+                    .unwrap_or(o.bytes.len());
+                OsString::from_vec(o.bytes[..len].to_vec())
+            })
+            .collect()
+    }
+
+    /// The contents of symbolic standard input (from KLEE's POSIX runtime
+    /// '-sym-stdin' option).
+    pub fn stdin(&self) -> Option<&[u8]> {
+        self.posix_objects()
+            .iter()
+            .find(|o| o.name == "stdin")
+            .map(|o| &o.bytes[..])
+    }
+
+    /// The names and contents of the symbolic files (from KLEE's POSIX runtime
+    /// '-sym-files' option).
+    pub fn sym_files(&self) -> Vec<(&str, &[u8])> {
+        self.posix_objects()
+            .iter()
+            .filter_map(|o| Some((sym_file_name(&o.name)?, &o.bytes[..])))
+            .collect()
+    }
+
+    /// The objects that the program makes symbolic, in the order that they
+    /// were created.
+    pub fn program_objects(&self) -> &[KTestObject] {
+        &self.objects[self.posix_objects().len()..]
+    }
+
+    /// The bytes of the objects that the program makes symbolic, in the order
+    /// that they were created (i.e., the order in which a fuzzing harness
+    /// reads them from its input).
+    pub fn program_bytes(&self) -> Vec<u8> {
+        self.program_objects()
+            .iter()
+            .flat_map(|o| o.bytes.iter().copied())
+            .collect()
+    }
+
+    /// Remove the objects created by KLEE's POSIX runtime, leaving only the
+    /// objects that the program itself makes symbolic (which is what the
+    /// kleeRuntest library expects when replaying natively).
+    pub fn without_posix_objects(mut self) -> KTest {
+        let objects = self.objects.split_off(self.posix_objects().len());
+        KTest { objects, ..self }
+    }
+
+    /// The objects created by KLEE's POSIX runtime (before the program runs).
+    /// The runtime creates them first, and "model_version" last, so they are
+    /// found by position rather than by name (the program can use the same
+    /// names).
+    fn posix_objects(&self) -> &[KTestObject] {
+        match self.objects.iter().position(|o| o.name == "model_version") {
+            Some(last) => &self.objects[..=last],
+            None => &[],
+        }
+    }
+}
+
+/// Is `name` the name of a symbolic argument created by KLEE's POSIX runtime
+/// ("arg00", "arg01", ...)?
+fn is_sym_arg(name: &str) -> bool {
+    match name.strip_prefix("arg") {
+        Some(n) => n.len() == 2 && n.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// If `name` is the name of the contents of a symbolic file created by KLEE's
+/// POSIX runtime ("A-data", "B-data", ...), the name of the file.
+fn sym_file_name(name: &str) -> Option<&str> {
+    let file = name.strip_suffix("-data")?;
+    if file.len() == 1 && file.bytes().all(|b| b.is_ascii_uppercase()) {
+        Some(file)
+    } else {
+        None
+    }
+}
+
+/// A cursor for reading a ktest file.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos:  usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data(String::from("truncated ktest file")))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn block(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        Ok(String::from_utf8_lossy(self.block()?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let ktest = KTest {
+            args:         vec![String::from("main.bc")],
+            sym_argvs:    0,
+            sym_argv_len: 0,
+            objects:      vec![
+                KTestObject {
+                    name:  String::from("model_version"),
+                    bytes: vec![1, 0, 0, 0],
+                },
+                KTestObject {
+                    name:  String::from("x"),
+                    bytes: vec![42, 0],
+                },
+            ],
+        };
+        let file = std::env::temp_dir().join(format!("ktest-{}.ktest", std::process::id()));
+        ktest.write(&file).unwrap();
+        let copy = KTest::read(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(copy, ktest);
+        assert_eq!(copy.program_bytes(), vec![42, 0]);
+    }
+
+    #[test]
+    fn posix_objects() {
+        let object = |name: &str, bytes: &[u8]| KTestObject {
+            name:  String::from(name),
+            bytes: bytes.to_vec(),
+        };
+        let ktest = KTest {
+            args:         vec![String::from("main.bc")],
+            sym_argvs:    1,
+            sym_argv_len: 2,
+            objects:      vec![
+                object("arg00", b"a\0"),
+                object("n_args", &[1, 0, 0, 0]),
+                object("stdin", b"in"),
+                object("stdin-stat", &[0; 4]),
+                object("model_version", &[1, 0, 0, 0]),
+                // The program can use the names of the runtime's objects.
+                object("stdin", &[7]),
+                object("arg0", &[8]),
+            ],
+        };
+        assert_eq!(ktest.sym_args(), vec![OsString::from("a")]);
+        assert_eq!(ktest.stdin(), Some(&b"in"[..]));
+        assert_eq!(ktest.program_bytes(), vec![7, 8]);
+        assert_eq!(
+            ktest.clone().without_posix_objects().objects,
+            ktest.objects[5..]
+        );
+
+        // Without the POSIX runtime, all the objects are the program's.
+        let ktest = KTest {
+            objects: ktest.objects[5..].to_vec(),
+            ..ktest
+        };
+        assert_eq!(ktest.stdin(), None);
+        assert_eq!(ktest.program_bytes(), vec![7, 8]);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+////////////////////////////////////////////////////////////////////////////////
+// Reading the files that KLEE writes to its output directory: the test cases
+// ("testNNNNNN.ktest") and the reports of the errors that they trigger
+// ("testNNNNNN.KIND.err").
+//
+// This lets tools work with KLEE's results without running 'ktest-tool' or
+// relying on the names of the files.
+////////////////////////////////////////////////////////////////////////////////
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+mod err;
+mod ktest;
+
+pub use crate::{
+    err::{ErrorKind, Frame, KleeError},
+    ktest::{KTest, KTestObject},
+};
+
+/// A test case generated by KLEE (one per path explored).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    /// The number of the test case (NNNNNN)
+    pub id:    u32,
+    /// The ktest file
+    pub ktest: PathBuf,
+    /// The error report, if the path ended with an error
+    pub error: Option<PathBuf>,
+}
+
+impl TestCase {
+    /// Read the ktest file of the test case.
+    pub fn read_ktest(&self) -> io::Result<KTest> {
+        KTest::read(&self.ktest)
+    }
+
+    /// Read the error report of the test case (if there is one).
+    pub fn read_error(&self) -> io::Result<Option<KleeError>> {
+        self.error.as_deref().map(KleeError::read).transpose()
+    }
+}
+
+/// Find the test cases in a KLEE output directory, ordered by number.
+pub fn test_cases(dir: &Path) -> io::Result<Vec<TestCase>> {
+    let mut tests = Vec::new();
+    let mut errors = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        if let Some(id) = name.strip_suffix(".ktest").and_then(test_id) {
+            tests.push(TestCase {
+                id,
+                ktest: path.clone(),
+                error: None,
+            });
+        } else if let Some(id) = name
+            .strip_suffix(".err")
+            .and_then(|n| test_id(n.split('.').next()?))
+        {
+            errors.push((id, path.clone()));
+        }
+    }
+
+    tests.sort_unstable_by_key(|t| t.id);
+    for (id, error) in errors {
+        if let Ok(i) = tests.binary_search_by_key(&id, |t| t.id) {
+            tests[i].error = Some(error);
+        }
+    }
+    Ok(tests)
+}
+
+/// The number of a test case from the stem of one of its files ("test000042").
+fn test_id(stem: &str) -> Option<u32> {
+    let digits = stem.strip_prefix("test")?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// An error caused by a malformed file.
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
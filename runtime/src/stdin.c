@@ -0,0 +1,47 @@
+// Copyright 2021 The Rust verification tools Authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A model of symbolic standard input for backends that don't have one.
+// (KLEE uses the model in its POSIX runtime instead, so this file is
+// not part of rvt-klee.bc.)
+//
+// Standard input contains __rvt_sym_stdin_size arbitrary bytes (like
+// KLEE's '-sym-stdin') and all other file descriptors are invalid.
+//
+// The model is only used when standard input is symbolic: then
+// 'rvt-patch-llvm --sym-stdin=SIZE' replaces all calls to 'read' with
+// calls to '__rvt_sym_stdin_read'. Otherwise, 'read' is left alone.
+
+#include <errno.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <sys/types.h>
+
+extern uint8_t __VERIFIER_nondet_u8(void);
+
+// The size of standard input.
+// This is set by 'rvt-patch-llvm --sym-stdin=SIZE'.
+size_t __rvt_sym_stdin_size = 0;
+
+static size_t stdin_position = 0;
+
+ssize_t __rvt_sym_stdin_read(int fd, void *buf, size_t count) {
+    if (fd != 0) {
+        errno = EBADF;
+        return -1;
+    }
+    size_t n = __rvt_sym_stdin_size - stdin_position;
+    if (count < n) {
+        n = count;
+    }
+    for(size_t i = 0; i < n; ++i) {
+        ((uint8_t*)buf)[i] = __VERIFIER_nondet_u8();
+    }
+    stdin_position += n;
+    return n;
+}
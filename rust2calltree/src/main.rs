@@ -14,6 +14,8 @@ use std::io::{prelude::*, BufReader, BufWriter};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+mod summary;
+
 // Command line argument parsing
 #[derive(StructOpt)]
 #[structopt(
@@ -35,6 +37,18 @@ struct Opt {
     )]
     output: PathBuf,
 
+    /// Print the N functions with the highest inclusive cost
+    #[structopt(long, name = "N")]
+    top: Option<usize>,
+
+    /// The event used to rank functions in the --top report (default: "I" if present, otherwise the first event)
+    #[structopt(long, name = "EVENT")]
+    event: Option<String>,
+
+    /// Merge the monomorphised copies of generic functions in the --top report
+    #[structopt(long)]
+    collapse: bool,
+
     /// Increase message verbosity
     #[structopt(short, long, parse(from_occurrences))]
     verbosity: usize,
@@ -62,8 +76,13 @@ fn main() -> Result<()> {
         File::create(&output).with_context(|| format!("can't open output file '{}'", output))?;
     let mut output = BufWriter::new(&output);
 
+    let mut summary = summary::Summary::new(opt.collapse);
+
     for line in input.lines() {
         let mut line = line?;
+        if opt.top.is_some() {
+            summary.add_line(&line)?;
+        }
         if let Some(s) = line.strip_prefix("fn=") {
             line = format!("fn={:#}", demangle(s));
         } else if let Some(s) = line.strip_prefix("cfn=") {
@@ -72,5 +91,9 @@ fn main() -> Result<()> {
         writeln!(output, "{}", line)?;
     }
 
+    if let Some(top) = opt.top {
+        summary.print(top, opt.event.as_deref())?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,196 @@
+// Copyright 2020-2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Hot-spot summary of a callgrind profile: the inclusive and exclusive cost of
+// each (demangled) function.
+//
+// Only the parts of the callgrind format that matter for the costs are
+// parsed: the "positions:" and "events:" headers, the "fn=", "cfn=" and
+// "calls=" lines (with name compression) and the cost lines.
+
+use anyhow::{bail, Context, Result};
+use rustc_demangle::demangle;
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct Cost {
+    exclusive: Vec<u64>,
+    inclusive: Vec<u64>,
+}
+
+fn add(total: &mut Vec<u64>, costs: &[u64]) {
+    if total.len() < costs.len() {
+        total.resize(costs.len(), 0);
+    }
+    for (t, c) in total.iter_mut().zip(costs) {
+        *t += c;
+    }
+}
+
+pub struct Summary {
+    /// Merge monomorphised copies of generic functions
+    collapse: bool,
+    positions: usize,
+    events: Vec<String>,
+    /// Compressed names: "(id) name" defines id, "(id)" refers to it
+    names: HashMap<String, String>,
+    costs: HashMap<String, Cost>,
+    current: Option<String>,
+    callee: Option<String>,
+    /// The next cost line is the inclusive cost of a call
+    in_call: bool,
+}
+
+impl Summary {
+    pub fn new(collapse: bool) -> Summary {
+        Summary {
+            collapse,
+            positions: 1,
+            events: Vec::new(),
+            names: HashMap::new(),
+            costs: HashMap::new(),
+            current: None,
+            callee: None,
+            in_call: false,
+        }
+    }
+
+    /// Process one line of the profile.
+    pub fn add_line(&mut self, line: &str) -> Result<()> {
+        if let Some(positions) = line.strip_prefix("positions:") {
+            self.positions = positions.split_whitespace().count();
+        } else if let Some(events) = line.strip_prefix("events:") {
+            self.events = events.split_whitespace().map(String::from).collect();
+        } else if let Some(name) = line.strip_prefix("fn=") {
+            self.current = Some(self.name(name));
+        } else if let Some(name) = line.strip_prefix("cfn=") {
+            self.callee = Some(self.name(name));
+        } else if line.starts_with("calls=") {
+            self.in_call = true;
+        } else if line.starts_with(|c: char| c.is_ascii_digit() || "+-*".contains(c)) {
+            let costs = line
+                .split_whitespace()
+                .skip(self.positions)
+                .map(str::parse)
+                .collect::<Result<Vec<u64>, _>>()
+                .with_context(|| format!("invalid cost line '{}'", line))?;
+            let current = match &self.current {
+                Some(current) => current.clone(),
+                None => bail!("cost line '{}' is not in a function", line),
+            };
+            let cost = self.costs.entry(current).or_default();
+            if std::mem::replace(&mut self.in_call, false) {
+                // Recursive calls would be counted twice.
+                if self.callee.as_ref() != self.current.as_ref() {
+                    add(&mut cost.inclusive, &costs);
+                }
+            } else {
+                add(&mut cost.exclusive, &costs);
+                add(&mut cost.inclusive, &costs);
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand a (possibly compressed) mangled name and demangle it.
+    fn name(&mut self, name: &str) -> String {
+        let name = match name.strip_prefix('(').and_then(|n| n.split_once(')')) {
+            Some((id, "")) => self.names.get(id).cloned().unwrap_or_default(),
+            Some((id, name)) => {
+                let name = name.trim_start().to_string();
+                self.names.insert(id.to_string(), name.clone());
+                name
+            }
+            None => name.to_string(),
+        };
+        if self.collapse {
+            collapse_generics(&format!("{:#}", demangle(&name)))
+        } else {
+            // Keep the hash to distinguish monomorphised copies.
+            format!("{}", demangle(&name))
+        }
+    }
+
+    /// Print the `top` functions with the highest inclusive cost of `event`
+    /// (by default, "I" if there is such an event and otherwise the first
+    /// event).
+    pub fn print(&self, top: usize, event: Option<&str>) -> Result<()> {
+        let event = match event {
+            Some(event) => event,
+            None if self.events.iter().any(|e| e == "I") => "I",
+            None => self.events.first().map_or("", String::as_str),
+        };
+        let index = match self.events.iter().position(|e| e == event) {
+            Some(index) => index,
+            None => bail!(
+                "unknown event '{}' (the events are: {})",
+                event,
+                self.events.join(" ")
+            ),
+        };
+
+        let get = |costs: &[u64]| costs.get(index).copied().unwrap_or(0);
+        let total: u64 = self.costs.values().map(|c| get(&c.exclusive)).sum();
+        let percent = |n: u64| 100.0 * n as f64 / total.max(1) as f64;
+
+        let mut functions: Vec<_> = self.costs.iter().collect();
+        functions.sort_by(|(f1, c1), (f2, c2)| {
+            get(&c2.inclusive)
+                .cmp(&get(&c1.inclusive))
+                .then_with(|| f1.cmp(f2))
+        });
+
+        println!("Total {}: {}", event, total);
+        println!(
+            "{:>14} {:>7} {:>14} {:>7}  Function",
+            "Inclusive", "%", "Exclusive", "%"
+        );
+        for (function, cost) in functions.into_iter().take(top) {
+            let (inclusive, exclusive) = (get(&cost.inclusive), get(&cost.exclusive));
+            println!(
+                "{:>14} {:>6.2}% {:>14} {:>6.2}%  {}",
+                inclusive,
+                percent(inclusive),
+                exclusive,
+                percent(exclusive),
+                function
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Remove the generic arguments ("::<...>") from a demangled name so that all
+/// the monomorphised copies of a function have the same name.
+fn collapse_generics(name: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if depth == 0 && rest.starts_with("::<") {
+            depth = 1;
+            rest = &rest[3..];
+            continue;
+        }
+        if rest.starts_with("->") {
+            if depth == 0 {
+                result.push_str("->");
+            }
+            rest = &rest[2..];
+            continue;
+        }
+        match c {
+            '<' if depth > 0 => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => result.push(c),
+            _ => (),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
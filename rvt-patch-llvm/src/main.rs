@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use log::info;
+use log::{info, warn};
 use regex::Regex;
 use std::collections::HashMap;
 use std::ffi::OsStr;
@@ -65,6 +65,25 @@ struct Opt {
     #[structopt(long)]
     smack: bool,
 
+    /// Fuzzing preparation (libFuzzer provides main)
+    #[structopt(long)]
+    fuzz: bool,
+
+    /// Generate a function '__rvt_main' that runs the initializers with the
+    /// arguments from '--arg' and then calls ENTRY (for SeaHorn and Smack, which
+    /// don't pass arguments to the program)
+    #[structopt(long, value_name = "ENTRY", conflicts_with = "initializers")]
+    wrap_main: Option<String>,
+
+    /// Argument to pass to the program (with '--wrap-main')
+    #[structopt(long = "arg", value_name = "ARG", number_of_values = 1)]
+    args: Vec<String>,
+
+    /// Make standard input symbolic, "SIZE" bytes long (using the model of
+    /// standard input in the runtime library)
+    #[structopt(long, value_name = "SIZE")]
+    sym_stdin: Option<u64>,
+
     /// Increase message verbosity
     #[structopt(short, long, parse(from_occurrences))]
     verbosity: usize,
@@ -147,6 +166,8 @@ fn main() {
 
         handle_panic(&context, &module);
 
+        handle_is_replay(&context, &module);
+
         replace_def_with_dec(
             &module,
             &Regex::new(r"^_ZN3std2io5stdio7_eprint17h[a-f0-9]{16}E$").unwrap(),
@@ -166,7 +187,7 @@ fn main() {
         );
         replace_def_with_dec(
             &module,
-            &Regex::new(r"^__VERIFIER_nondet_[iu]\d+$").unwrap(),
+            &Regex::new(r"^__VERIFIER_nondet_[iu](\d+|size)$").unwrap(),
         );
         replace_def_with_dec(
             &module,
@@ -176,6 +197,20 @@ fn main() {
             &module,
             &Regex::new(r"^_ZN3std2io5stdio6_print17h[a-f0-9]{16}E$").unwrap(),
         );
+
+        handle_is_replay(&context, &module);
+    }
+
+    if opt.fuzz {
+        handle_main(&module);
+    }
+
+    if let Some(entry) = &opt.wrap_main {
+        build_main_wrapper(&context, &mut module, entry, &opt.args);
+    }
+
+    if let Some(size) = opt.sym_stdin {
+        handle_sym_stdin(&context, &module, size);
     }
 
     // Write output file
@@ -224,6 +259,74 @@ fn handle_initializers(context: &Context, module: &mut Module) {
     }
 }
 
+/// Generate a function '__rvt_main' that calls the initializers with the
+/// command line arguments (this makes std::env::args() work) and then calls
+/// `entry`:
+///
+///     define void @__rvt_main() {
+///     entry:
+///       %argv = alloca i8*, i32 3
+///       ... store pointers to "prog", "arg1" and null in %argv ...
+///       call void @__init_function(i32 2, i8** %argv, i8** null)
+///       call void @entry()
+///       ret void
+///     }
+///
+fn build_main_wrapper(context: &Context, module: &mut Module, entry: &str, args: &[String]) {
+    let entry_fn = module
+        .get_function(entry)
+        .unwrap_or_else(|| panic!("Unable to find '{}' function", entry));
+    let initializer = collect_initializers(context, module, ".init_array", "__init_function");
+
+    let i32_type = context.i32_type();
+    let i8_type = context.i8_type();
+    let pi8_type = i8_type.ptr_type(AddressSpace::Generic);
+    let ppi8_type = pi8_type.ptr_type(AddressSpace::Generic);
+
+    let function = module.add_function("__rvt_main", context.void_type().fn_type(&[], false), None);
+    let basic_block = context.append_basic_block(function, "entry");
+    let builder = context.create_builder();
+    builder.position_at_end(basic_block);
+
+    // argv[0] is the name of the program
+    let argv_strings: Vec<&str> = std::iter::once(entry)
+        .chain(args.iter().map(String::as_str))
+        .collect();
+    let argc = i32_type.const_int(argv_strings.len() as u64, false);
+    let argv = builder.build_array_alloca(
+        pi8_type,
+        i32_type.const_int(argv_strings.len() as u64 + 1, false),
+        "argv",
+    );
+    for (i, s) in argv_strings.iter().enumerate() {
+        let string = builder.build_global_string_ptr(s, &format!("__rvt_arg{}", i));
+        let slot = unsafe { builder.build_gep(argv, &[i32_type.const_int(i as u64, false)], "") };
+        builder.build_store(slot, string.as_pointer_value());
+    }
+    let slot = unsafe { builder.build_gep(argv, &[argc], "") };
+    builder.build_store(slot, pi8_type.const_null());
+
+    if let Some(initializer) = initializer {
+        let args: Vec<BasicValueEnum> = vec![
+            argc.as_basic_value_enum(),
+            argv.as_basic_value_enum(),
+            ppi8_type.const_null().as_basic_value_enum(),
+        ];
+        builder.build_call(initializer, &args, "");
+        info!(
+            "Inserted call to '{}' into '__rvt_main'",
+            initializer.get_name().to_str().unwrap()
+        )
+    }
+
+    builder.build_call(entry_fn, &[], "");
+    builder.build_return(None);
+    info!(
+        "Generated '__rvt_main' calling '{}' with arguments {:?}",
+        entry, args
+    );
+}
+
 /// Collect all the initializers in a section (whose name starts with 'prefix')
 /// into a single function that calls all the initializers.
 fn collect_initializers<'a>(
@@ -471,6 +574,43 @@ fn handle_panic(context: &Context, module: &Module) {
     }
 }
 
+/// Define `__VERIFIER_is_replay` to return 0. The C implementations (used when
+/// replaying a counterexample natively) read an environment variable, which
+/// the verifiers would treat as an arbitrary value.
+fn handle_is_replay(context: &Context, module: &Module) {
+    if let Some(fv) = module.get_function("__VERIFIER_is_replay") {
+        delete_body(&fv);
+        fv.set_linkage(Linkage::External);
+        let basic_block = context.append_basic_block(fv, "entry");
+        let builder = context.create_builder();
+        builder.position_at_end(basic_block);
+        builder.build_return(Some(&context.i32_type().const_int(0, false)));
+        info!("Replaced the body of '__VERIFIER_is_replay' with 'return 0'.");
+    }
+}
+
+/// Use the runtime library's model of standard input: replace all calls to
+/// 'read' with calls to the model, and set the size of standard input.
+fn handle_sym_stdin(context: &Context, module: &Module, size: u64) {
+    let model = match module.get_function("__rvt_sym_stdin_read") {
+        Some(model) => model,
+        None => {
+            warn!("Unable to find '__rvt_sym_stdin_read': standard input is not symbolic.");
+            return;
+        }
+    };
+    if let Some(read) = module.get_function("read") {
+        read.replace_all_uses_with(model);
+        info!("Replaced 'read' with '__rvt_sym_stdin_read'.");
+    }
+    if let Some(gv) = module.get_global("__rvt_sym_stdin_size") {
+        gv.set_initializer(&context.i64_type().const_int(size, false));
+        info!("Set the size of standard input to {}.", size);
+    } else {
+        warn!("Unable to find '__rvt_sym_stdin_size': standard input is not symbolic.");
+    }
+}
+
 /// Change a function to a declaration by
 /// deleting all basic blocks and modifying metadata
 /// such as personality_function, linkage, etc.
@@ -10,11 +10,17 @@
 #include <stdlib.h>
 #include <stdint.h>
 
+// The definitions in this file are used when the program is built
+// natively (e.g., for replaying a counterexample). They are weak so that the
+// counterexample harness generated by SeaHorn ('--cex') can override them.
+
+__attribute__((weak))
 void __VERIFIER_error() {
   fprintf(stderr, "ERROR: a verification assertion failed.");
   exit(1);
 }
 
+__attribute__((weak))
 void __VERIFIER_assume(int pred) {
   if (pred == 0) {
     fprintf(stderr, "ERROR: a verification assumption has been violated.");
@@ -22,17 +28,23 @@ void __VERIFIER_assume(int pred) {
   }
 }
 
-uint8_t   __VERIFIER_nondet_u8()    { return 0; }
-uint16_t  __VERIFIER_nondet_u16()   { return 0; }
-uint32_t  __VERIFIER_nondet_u32()   { return 0; }
-uint64_t  __VERIFIER_nondet_u64()   { return 0; }
-uintptr_t __VERIFIER_nondet_usize() { return 0; }
+__attribute__((weak)) uint8_t   __VERIFIER_nondet_u8()    { return 0; }
+__attribute__((weak)) uint16_t  __VERIFIER_nondet_u16()   { return 0; }
+__attribute__((weak)) uint32_t  __VERIFIER_nondet_u32()   { return 0; }
+__attribute__((weak)) uint64_t  __VERIFIER_nondet_u64()   { return 0; }
+__attribute__((weak)) uintptr_t __VERIFIER_nondet_usize() { return 0; }
+
+__attribute__((weak)) int8_t    __VERIFIER_nondet_i8()    { return 0; }
+__attribute__((weak)) int16_t   __VERIFIER_nondet_i16()   { return 0; }
+__attribute__((weak)) int32_t   __VERIFIER_nondet_i32()   { return 0; }
+__attribute__((weak)) int64_t   __VERIFIER_nondet_i64()   { return 0; }
+__attribute__((weak)) intptr_t  __VERIFIER_nondet_isize() { return 0; }
 
-int8_t    __VERIFIER_nondet_i8()    { return 0; }
-int16_t   __VERIFIER_nondet_i16()   { return 0; }
-int32_t   __VERIFIER_nondet_i32()   { return 0; }
-int64_t   __VERIFIER_nondet_i64()   { return 0; }
-intptr_t  __VERIFIER_nondet_isize() { return 0; }
+__attribute__((weak)) float     __VERIFIER_nondet_f32()   { return 0; }
+__attribute__((weak)) double    __VERIFIER_nondet_f64()   { return 0; }
 
-float     __VERIFIER_nondet_f32()   { return 0; }
-double    __VERIFIER_nondet_f64()   { return 0; }
+// cargo-verify sets SEAHORN_REPLAY when replaying a counterexample.
+// (During verification, rvt-patch-llvm replaces this with 'return 0'.)
+int __VERIFIER_is_replay() {
+  return getenv("SEAHORN_REPLAY") != NULL;
+}
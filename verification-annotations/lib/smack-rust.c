@@ -29,13 +29,46 @@
     return ret;                                                                \
   }
 
+#if CARGO_BUILD
+#include <stdio.h>
+
+// When replaying a counterexample, cargo-verify writes the values returned by
+// the __VERIFIER_nondet_* functions to the file named by SMACK_REPLAY_FILE,
+// one per line (as 128-bit two's complement bit patterns, in decimal).
+// (scanf can't read 128-bit integers, so the digits are read one by one.)
+static unsigned __int128 next_replay_value(void) {
+  static FILE *replay_file = NULL;
+  static int initialized = 0;
+  unsigned __int128 value = 0;
+  int c;
+  if (!initialized) {
+    const char *path = getenv("SMACK_REPLAY_FILE");
+    if (path)
+      replay_file = fopen(path, "r");
+    initialized = 1;
+  }
+  if (!replay_file)
+    return 0;
+  do {
+    c = fgetc(replay_file);
+  } while (c != EOF && (c < '0' || c > '9'));
+  while (c >= '0' && c <= '9') {
+    value = value * 10 + (c - '0');
+    c = fgetc(replay_file);
+  }
+  return value;
+}
+
+// (During verification, rvt-patch-llvm replaces this with 'return 0'.)
+int __VERIFIER_is_replay(void) { return getenv("SMACK_REPLAY_FILE") != NULL; }
+#endif
+
 #define mk_dummy_int_nondet_def(size)                                          \
   mk_signed_type(size) __VERIFIER_nondet_i##size(void) {                       \
-    return *((mk_signed_type(size) *)malloc(sizeof(mk_signed_type(size))));    \
+    return (mk_signed_type(size))next_replay_value();                          \
   }                                                                            \
   mk_unsigned_type(size) __VERIFIER_nondet_u##size(void) {                     \
-    return *(                                                                  \
-        (mk_unsigned_type(size) *)malloc(sizeof(mk_unsigned_type(size))));     \
+    return (mk_unsigned_type(size))next_replay_value();                        \
   }
 
 #define mk_dummy_fp_nondet_def(ty)                                             \
@@ -47,6 +80,10 @@ mk_dummy_int_nondet_def(8) mk_dummy_int_nondet_def(16)
         mk_dummy_fp_nondet_def(float)
             mk_dummy_fp_nondet_def(double) void __VERIFIER_assert(int x) {}
 void __VERIFIER_assume(int x) {}
+intptr_t __VERIFIER_nondet_isize(void) { return (intptr_t)next_replay_value(); }
+uintptr_t __VERIFIER_nondet_usize(void) {
+  return (uintptr_t)next_replay_value();
+}
 #else
 mk_smack_nondet_decl(8) mk_smack_nondet_decl(16) mk_smack_nondet_decl(32)
     mk_smack_nondet_decl(64) mk_smack_nondet_def(8) mk_smack_nondet_def(16)
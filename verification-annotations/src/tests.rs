@@ -91,6 +91,43 @@ fn t5() {
     verifier::assert_ne!(a, a + 1);
 }
 
+// Tests that are expected to fail with a particular status
+// rather than with a particular panic message.
+#[cfg_attr(not(feature = "verifier-crux"), test)]
+#[cfg_attr(feature = "verifier-crux", crux_test)]
+fn t6() {
+    verifier::expect_status(verifier::ExpectedStatus::Overflow);
+
+    let a: u32 = verifier::AbstractValue::abstract_value();
+    let b: u32 = verifier::AbstractValue::abstract_value();
+    let r = a * b;
+    verifier::assume(4 <= a && a <= 7);
+    verifier::assume(5 <= b && b <= 8);
+    assert!(20 <= r && r <= 56);
+}
+
+#[cfg_attr(not(feature = "verifier-crux"), test)]
+#[cfg_attr(feature = "verifier-crux", crux_test)]
+fn t7() {
+    verifier::expect_status(verifier::ExpectedStatus::OutOfBounds);
+
+    let v = [1u32, 2, 3];
+    let i: usize = verifier::AbstractValue::abstract_value();
+    verifier::assume(i <= 3);
+    assert!(v[i] <= 3);
+}
+
+#[cfg_attr(not(feature = "verifier-crux"), test)]
+#[cfg_attr(feature = "verifier-crux", crux_test)]
+fn t8() {
+    verifier::expect_status(verifier::ExpectedStatus::Reachable);
+
+    let a: u32 = verifier::AbstractValue::abstract_value();
+    if a == 42 {
+        verifier::unreachable!();
+    }
+}
+
 // KLEE-only test of get_concrete_value and is_symbolic
 #[cfg(feature = "verifier-klee")]
 #[test]
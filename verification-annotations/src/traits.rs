@@ -23,6 +23,18 @@ use crate::verifier::assume;
 pub trait VerifierNonDet {
     fn verifier_nondet(self) -> Self;
 
+    /// Like `verifier_nondet` but `name` identifies the value in
+    /// counterexamples (e.g., it is the name of the KLEE ktest object).
+    /// `verifier::nondet_named` also prints the value when replaying
+    /// them.
+    fn verifier_nondet_named(self, name: &'static str) -> Self
+    where
+        Self: Sized,
+    {
+        let _ = name;
+        self.verifier_nondet()
+    }
+
     #[cfg(feature = "verifier-klee")]
     /// Obtain a concrete value satisfying the constraints
     /// currently in force for the expression.
@@ -76,8 +76,11 @@ pub fn report_error(message: &str) {
 }
 
 /// Declare that failure is the expected behaviour
-pub fn expect_raw(_msg: &str) {
-    panic!("not implemented")
+///
+/// cargo-verify reads this from the output of crux-mir, which is printed
+/// before the result of the test.
+pub fn expect_raw(msg: &str) {
+    println!("VERIFIER_EXPECT: {}", msg)
 }
 
 /// Declare that failure is the expected behaviour
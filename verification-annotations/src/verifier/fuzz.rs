@@ -0,0 +1,224 @@
+// Copyright 2021 The Propverify authors
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/////////////////////////////////////////////////////////////////
+// Fuzzing with libFuzzer
+//
+// Non-deterministic values are taken from the input that libFuzzer
+// supplies (or, when replaying a crash, from the file in the
+// environment variable RVT_FUZZ_INPUT) and inputs that don't
+// satisfy the assumptions (or are too short) are rejected.
+//
+// Rejecting an input panics with a `Rejected` payload, which
+// `__rvt_fuzz_run` catches so that libFuzzer can carry on with
+// the next input. (The fuzz targets are built with panic=unwind
+// so that the values owned by the test are dropped.)
+//
+// This is also the verifier used when no verifier feature is
+// selected: the regression tests that cargo-verify generates
+// run the tests with `run_with_input`.
+/////////////////////////////////////////////////////////////////
+
+use std::{
+    cell::{Cell, RefCell},
+    convert::TryInto,
+    panic,
+};
+
+pub use crate::traits::*;
+
+thread_local! {
+    /// The current input and the position of the next unused byte.
+    static INPUT: RefCell<Option<(Vec<u8>, usize)>> = RefCell::new(None);
+
+    /// Is a test being run by `__rvt_fuzz_run`?
+    static RUNNING: Cell<bool> = Cell::new(false);
+}
+
+/// The panic payload used to reject an input.
+struct Rejected;
+
+/// Run `test` with an input from libFuzzer.
+///
+/// cargo-verify generates a libFuzzer fuzz target (LLVMFuzzerTestOneInput)
+/// for each test, which calls this function with the input and the test.
+///
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[cfg(feature = "verifier-fuzz")]
+#[no_mangle]
+#[allow(improper_ctypes_definitions)] // `test` is a Rust function
+pub unsafe extern "C" fn __rvt_fuzz_run(data: *const u8, size: usize, test: fn()) {
+    let input = std::slice::from_raw_parts(data, size);
+    if panic::catch_unwind(|| run_with_input(input, test)).is_err() {
+        // The test panicked (and the panic hook has printed the
+        // message): libFuzzer reports the abort signal as a crash.
+        std::process::abort()
+    }
+}
+
+/// Run `test` with the non-deterministic values taken from `input`.
+/// Returns normally if the input is rejected.
+///
+/// This is also used by the regression tests that cargo-verify
+/// generates from KLEE's counterexamples (`--emit-regression-tests`).
+pub fn run_with_input(input: &[u8], test: fn()) {
+    INPUT.with(|i| *i.borrow_mut() = Some((input.to_vec(), 0)));
+
+    RUNNING.with(|r| r.set(true));
+    let result = panic::catch_unwind(test);
+    RUNNING.with(|r| r.set(false));
+
+    if let Err(payload) = result {
+        if !payload.is::<Rejected>() {
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+/// The input used when replaying a crash.
+fn replay_input() -> (Vec<u8>, usize) {
+    let input = std::env::var_os("RVT_FUZZ_INPUT")
+        .and_then(|file| std::fs::read(file).ok())
+        .unwrap_or_default();
+    (input, 0)
+}
+
+/// Take the next `n` bytes of the input, rejecting the input if it is too
+/// short.
+fn next_bytes(n: usize) -> Vec<u8> {
+    let bytes = INPUT.with(|i| {
+        let mut i = i.borrow_mut();
+        let (input, pos) = i.get_or_insert_with(replay_input);
+        let bytes = input.get(*pos..*pos + n)?.to_vec();
+        *pos += n;
+        Some(bytes)
+    });
+    // (The input must not be borrowed when rejecting.)
+    match bytes {
+        Some(bytes) => bytes,
+        None => reject(),
+    }
+}
+
+macro_rules! make_nondet {
+    ($typ:ty) => {
+        impl VerifierNonDet for $typ {
+            fn verifier_nondet(self) -> Self {
+                let bytes = next_bytes(std::mem::size_of::<$typ>());
+                Self::from_ne_bytes(bytes[..].try_into().unwrap())
+            }
+        }
+    };
+}
+
+make_nondet!(u8);
+make_nondet!(u16);
+make_nondet!(u32);
+make_nondet!(u64);
+make_nondet!(u128);
+make_nondet!(usize);
+
+make_nondet!(i8);
+make_nondet!(i16);
+make_nondet!(i32);
+make_nondet!(i64);
+make_nondet!(i128);
+make_nondet!(isize);
+
+make_nondet!(f32);
+make_nondet!(f64);
+
+impl VerifierNonDet for bool {
+    fn verifier_nondet(self) -> Self {
+        // Use every byte (instead of rejecting values other than 0 and 1)
+        // so that the fuzzer does not waste time on rejected inputs.
+        next_bytes(1)[0] & 1 == 1
+    }
+}
+
+/// Reject the current execution with a verification failure.
+///
+/// In almost all circumstances, `report_error` should
+/// be used instead because it generates an error message.
+pub fn abort() -> ! {
+    if RUNNING.with(Cell::get) {
+        // `__rvt_fuzz_run` aborts (libFuzzer reports the abort signal as
+        // a crash) and `run_with_input` fails the regression test.
+        // (The message has already been printed.)
+        panic::resume_unwind(Box::new("verification failure"))
+    }
+    std::process::abort()
+}
+
+/// Assume that condition `cond` is true
+///
+/// Any inputs found must satisfy this assumption.
+pub fn assume(cond: bool) {
+    if !cond {
+        reject();
+    }
+}
+
+/// Reject the current input with a verification success.
+/// This is equivalent to `assume(false)`
+/// and the opposite of `report_error(...)`.
+///
+/// Typical usage is in generating symbolic values when the value
+/// does not meet some criteria.
+pub fn reject() -> ! {
+    if RUNNING.with(Cell::get) {
+        // (resume_unwind does not call the panic hook, so nothing is printed.)
+        panic::resume_unwind(Box::new(Rejected))
+    }
+    if is_replay() {
+        // When replaying a crash with 'cargo test', there is no fuzz target
+        // to return to.
+        std::process::exit(0)
+    }
+    // A test that needs an input was run without one (e.g., by a plain
+    // 'cargo test'): exiting would hide the results of the other tests.
+    panic!("no input for the non-deterministic values (use 'cargo verify' or 'verifier::run_with_input')")
+}
+
+/// Detect whether the program is being fuzzed
+/// or is replaying a crash found by the fuzzer.
+///
+/// This is used to decide whether to display the values of
+/// variables.
+pub fn is_replay() -> bool {
+    std::env::var_os("RVT_FUZZ_INPUT").is_some()
+}
+
+/// Reject the current execution with a verification failure
+/// and an error message.
+pub fn report_error(message: &str) -> ! {
+    // Mimic the format of klee_report_error
+    // (We don't use klee_report_error because it is not
+    // supported by the kleeRuntest library.)
+    eprintln!("FUZZ: ERROR:{}", message);
+    abort();
+}
+
+/// Declare that failure is the expected behaviour
+pub fn expect_raw(msg: &str) {
+    eprintln!("VERIFIER_EXPECT: {}", msg)
+}
+
+/// Declare that failure is the expected behaviour
+pub fn expect(msg: Option<&str>) {
+    match msg {
+        None => eprintln!("VERIFIER_EXPECT: should_panic"),
+        Some(msg) => eprintln!("VERIFIER_EXPECT: should_panic(expected = \"{}\")", msg),
+    }
+}
+
+/////////////////////////////////////////////////////////////////
+// End
+/////////////////////////////////////////////////////////////////
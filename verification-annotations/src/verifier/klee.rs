@@ -72,6 +72,28 @@ unsafe fn get_value_usize(x: usize) -> usize {
     klee_get_value_i64(x as i64) as usize
 }
 
+/// Make the bytes of `x` symbolic.
+///
+/// KLEE uses `name` (truncated to 63 bytes) as the name of
+/// the ktest object.
+unsafe fn make_symbolic<T>(x: &mut T, name: Option<&str>) {
+    let data: *mut u8 = x as *mut T as *mut u8;
+    let length = core::mem::size_of::<T>();
+    match name {
+        Some(name) => {
+            // KLEE copies the name so it can be on the stack.
+            let mut cname = [0u8; 64];
+            let len = core::cmp::min(name.len(), cname.len() - 1);
+            cname[..len].copy_from_slice(&name.as_bytes()[..len]);
+            klee_make_symbolic(data, length, cname.as_ptr() as *const i8)
+        }
+        None => {
+            let null = 0 as *const i8;
+            klee_make_symbolic(data, length, null)
+        }
+    }
+}
+
 /// Create instance for any type consisting of contiguous memory
 /// where all bit-patterns are legal values of the type.
 macro_rules! make_verifier_nondet {
@@ -79,12 +101,13 @@ macro_rules! make_verifier_nondet {
         impl VerifierNonDet for $typ {
             fn verifier_nondet(self) -> Self {
                 let mut r = self;
-                unsafe {
-                    let data: *mut u8 = &mut r as *mut $typ as *mut u8;
-                    let length = core::mem::size_of::<$typ>();
-                    let null = 0 as *const i8;
-                    klee_make_symbolic(data, length, null)
-                }
+                unsafe { make_symbolic(&mut r, None) }
+                return r;
+            }
+
+            fn verifier_nondet_named(self, name: &'static str) -> Self {
+                let mut r = self;
+                unsafe { make_symbolic(&mut r, Some(name)) }
                 return r;
             }
 
@@ -123,6 +146,13 @@ impl VerifierNonDet for bool {
         c == 1
     }
 
+    fn verifier_nondet_named(self, name: &'static str) -> Self {
+        let mut c = 0u8;
+        unsafe { make_symbolic(&mut c, Some(name)) }
+        assume(c == 0 || c == 1);
+        c == 1
+    }
+
     fn get_concrete_value(x: Self) -> Self {
         unsafe { klee_get_value_i32(x as i32) != 0 }
     }
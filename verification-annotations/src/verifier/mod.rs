@@ -35,6 +35,36 @@ mod smack;
 #[cfg(feature = "verifier-smack")]
 pub use smack::*;
 
+// Without a verifier, the non-deterministic values are read from an input,
+// like when fuzzing. This is what the regression tests generated by
+// cargo-verify (with '--emit-regression-tests') use in a plain `cargo test`.
+#[cfg(any(
+    feature = "verifier-fuzz",
+    all(
+        feature = "std",
+        not(any(
+            feature = "verifier-klee",
+            feature = "verifier-crux",
+            feature = "verifier-seahorn",
+            feature = "verifier-smack"
+        ))
+    )
+))]
+mod fuzz;
+#[cfg(any(
+    feature = "verifier-fuzz",
+    all(
+        feature = "std",
+        not(any(
+            feature = "verifier-klee",
+            feature = "verifier-crux",
+            feature = "verifier-seahorn",
+            feature = "verifier-smack"
+        ))
+    )
+))]
+pub use fuzz::*;
+
 #[cfg(feature = "std")]
 /// Allocate a symbolic vector of bytes
 pub fn verifier_nondet_bytes(n: usize) -> Vec<u8> {
@@ -76,11 +106,75 @@ impl<T: VerifierNonDet + Default> AbstractValue for T {
 }
 
 impl<T: VerifierNonDet + Default> Symbolic for T {
-    fn symbolic(_desc: &'static str) -> Self {
-        Self::verifier_nondet(Self::default())
+    fn symbolic(desc: &'static str) -> Self {
+        Self::verifier_nondet_named(Self::default(), desc)
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "verifier-crux")))]
+/// Create a non-deterministic value that is named `name` in
+/// counterexamples (see `VerifierNonDet::verifier_nondet_named`).
+/// When replaying a counterexample, its value is printed
+/// (e.g., "Value a: u32 = 17").
+pub fn nondet_named<T: VerifierNonDet + Default + std::fmt::Debug>(name: &'static str) -> T {
+    let r = T::verifier_nondet_named(T::default(), name);
+    if is_replay() {
+        println!("  Value {}: {} = {:?}", name, std::any::type_name::<T>(), r);
+    }
+    r
+}
+
+/// The ways that a test can be expected to fail (see `expect_status`).
+/// The names match the status reported by cargo-verify.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpectedStatus {
+    /// A failed assertion
+    AssertFailed,
+    /// An out of bounds array/slice access
+    OutOfBounds,
+    /// An arithmetic overflow
+    Overflow,
+    /// A panic that is not one of the more specific failures
+    Panic,
+    /// A call to `unreachable!()` was reached
+    Reachable,
+    /// The verifier ran out of time
+    Timeout,
+    /// A division (or remainder) by zero
+    DivisionByZero,
+    /// A null or dangling pointer, or freeing invalid memory
+    InvalidPointer,
+    /// Memory that was never freed
+    MemoryLeak,
+}
+
+impl ExpectedStatus {
+    /// The name of the status in cargo-verify's reports (e.g., "OUT_OF_BOUNDS").
+    pub fn name(self) -> &'static str {
+        match self {
+            ExpectedStatus::AssertFailed => "ASSERT_FAILED",
+            ExpectedStatus::OutOfBounds => "OUT_OF_BOUNDS",
+            ExpectedStatus::Overflow => "OVERFLOW",
+            ExpectedStatus::Panic => "PANIC",
+            ExpectedStatus::Reachable => "REACHABLE",
+            ExpectedStatus::Timeout => "TIMEOUT",
+            ExpectedStatus::DivisionByZero => "DIVISION_BY_ZERO",
+            ExpectedStatus::InvalidPointer => "INVALID_POINTER",
+            ExpectedStatus::MemoryLeak => "MEMORY_LEAK",
+        }
     }
 }
 
+#[cfg(feature = "std")]
+/// Declare that the test is expected to fail with `status`
+/// (e.g., an overflow) rather than with a particular panic message.
+///
+/// Seahorn and SMACK do not run the program, so they only see the
+/// expectation when the counterexample is replayed (`--replay`).
+pub fn expect_status(status: ExpectedStatus) {
+    expect_raw(&format!("status({})", status.name()))
+}
+
 // Macros
 
 #[macro_export]
@@ -152,7 +246,7 @@ macro_rules! assert_ne {
 #[macro_export]
 macro_rules! unreachable {
     () => {
-        $crate::report_error("unreachable assertion was reached");
+        $crate::verifier::report_error("unreachable assertion was reached");
     };
 }
 
@@ -17,6 +17,7 @@ pub use crate::traits::*;
 extern "C" {
     fn __VERIFIER_error() -> !;
     fn __VERIFIER_assume(pred: i32);
+    fn __VERIFIER_is_replay() -> i32;
 }
 
 #[no_mangle]
@@ -54,14 +55,13 @@ pub fn reject() -> ! {
     panic!("Unreachable, should have been rejected!");
 }
 
-/// Detect whether the program is being run symbolically in KLEE
-/// or being replayed using the kleeRuntest runtime.
+/// Detect whether the program is being verified by SeaHorn
+/// or being replayed using a SeaHorn counterexample harness.
 ///
 /// This is used to decide whether to display the values of
 /// variables that may be either symbolic or concrete.
 pub fn is_replay() -> bool {
-    // panic!("SeaHorn doesn't support replay.")
-    false
+    unsafe { __VERIFIER_is_replay() != 0 }
 }
 
 /// Reject the current execution with a verification failure
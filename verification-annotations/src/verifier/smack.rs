@@ -18,6 +18,7 @@ use std::alloc::Layout;
 extern "C" {
     fn __VERIFIER_assert(pred: i32) -> !;
     fn __VERIFIER_assume(pred: i32);
+    fn __VERIFIER_is_replay() -> i32;
     pub fn malloc(size: usize) -> *mut u8;
     pub fn free(ptr: *mut u8);
     fn memset(ptr: *mut u8, ch: i32, count: usize);
@@ -58,14 +59,13 @@ pub fn reject() -> ! {
     panic!("Unreachable, should have been rejected!");
 }
 
-/// Detect whether the program is being run symbolically in KLEE
-/// or being replayed using the kleeRuntest runtime.
+/// Detect whether the program is being verified by SMACK
+/// or being replayed using the values from a SMACK counterexample.
 ///
 /// This is used to decide whether to display the values of
 /// variables that may be either symbolic or concrete.
 pub fn is_replay() -> bool {
-    // panic!("SMACK doesn't support replay.")
-    false
+    unsafe { __VERIFIER_is_replay() != 0 }
 }
 
 /// Reject the current execution with a verification failure